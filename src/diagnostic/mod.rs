@@ -0,0 +1,336 @@
+use std::fmt;
+use crate::scanner::token::{Token, TokenType};
+
+// Every phase only reports errors for now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Part of the pipeline that found the problem
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Scan,
+    Parse,
+    Resolve,
+    Compile,
+    Runtime,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Scan => write!(f, "scan"),
+            Phase::Parse => write!(f, "parse"),
+            Phase::Resolve => write!(f, "resolve"),
+            Phase::Compile => write!(f, "compile"),
+            Phase::Runtime => write!(f, "runtime"),
+        }
+    }
+}
+
+/// Region of the source a diagnostic points at. Lines and columns start
+/// at 1 and count characters, `end_col` is one past the last character.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl Span {
+    /// Region of `text` starting at `line` and `col`, at least one
+    /// character wide. A line break ending the text is left out.
+    pub fn covering(line: usize, col: usize, text: &str) -> Span {
+        let text = text.trim_end_matches(['\n', '\r']);
+
+        match text.rsplit_once('\n') {
+            Some((before, last)) => Span {
+                line,
+                col,
+                end_line: line + before.matches('\n').count() + 1,
+                end_col: last.chars().count() + 1,
+            },
+            None => Span {
+                line,
+                col,
+                end_line: line,
+                end_col: col + text.chars().count().max(1),
+            },
+        }
+    }
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Span {
+        Span::covering(token.line, token.col, &String::from_utf8_lossy(&token.lexeme))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub phase: Phase,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    // Text printed next to the carets of the primary span
+    pub label: Option<String>,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(phase: Phase, code: &'static str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            phase,
+            code,
+            message,
+            span: None,
+            label: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Diagnostic {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, label: String) -> Diagnostic {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: String) -> Diagnostic {
+        self.secondary.push(Label { span, message });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    /// Describe a token the way it should be quoted inside a message
+    pub fn describe(token: &Token) -> String {
+        match token.typee {
+            TokenType::Eof => String::from("end of file"),
+            _ => format!("`{}`", String::from_utf8_lossy(&token.lexeme)),
+        }
+    }
+
+    /// Render in the style of rustc, quoting the lines of `source` the
+    /// diagnostic points at
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut output = format!("{}[{}]: {}\n", self.severity, self.code, self.message);
+
+        let primary = match self.span {
+            Some(span) => span,
+            None => {
+                for note in &self.notes {
+                    output.push_str(&format!("  = note: {}\n", note));
+                }
+                return output;
+            },
+        };
+
+        // Primary marker first, then the secondary ones
+        let mut markers: Vec<(Span, char, &str)> = vec![(primary, '^', self.label.as_deref().unwrap_or(""))];
+        for label in &self.secondary {
+            markers.push((label.span, '-', label.message.as_str()));
+        }
+
+        let mut lines: Vec<usize> = markers.iter().map(|(span, _, _)| span.line).collect();
+        lines.sort();
+        lines.dedup();
+
+        let width = lines.last().unwrap_or(&primary.line).to_string().len();
+        let gutter = " ".repeat(width);
+
+        output.push_str(&format!("{}--> {}:{}:{}\n", gutter, file_name, primary.line, primary.col));
+        output.push_str(&format!("{} |\n", gutter));
+
+        let source_lines: Vec<&str> = source.lines().collect();
+
+        for line in lines {
+            let text = source_lines.get(line.wrapping_sub(1)).copied().unwrap_or("");
+            output.push_str(&format!("{:>width$} | {}\n", line, text, width = width));
+
+            let text_len = text.chars().count();
+
+            for (span, marker, message) in markers.iter().filter(|(span, _, _)| span.line == line) {
+                // Only the first line of a span over several lines is underlined
+                let available = (text_len + 1).saturating_sub(span.col).max(1);
+                let len = match span.end_line == span.line {
+                    true => span.end_col.saturating_sub(span.col),
+                    false => available,
+                };
+                let underline = marker.to_string().repeat(len.min(available).max(1));
+                let padding = " ".repeat(span.col.saturating_sub(1));
+
+                let marker_line = format!("{} | {}{} {}", gutter, padding, underline, message);
+                output.push_str(marker_line.trim_end());
+                output.push('\n');
+            }
+        }
+
+        if !self.notes.is_empty() {
+            output.push_str(&format!("{} |\n", gutter));
+        }
+
+        for note in &self.notes {
+            output.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+
+        output
+    }
+
+    /// Encode as a single line JSON object. Positions are those of the
+    /// span, and all null when the diagnostic has no span.
+    pub fn to_json(&self, file_name: &str) -> String {
+        let (start_line, start_col, end_line, end_col) = match self.span {
+            Some(span) => (
+                span.line.to_string(),
+                span.col.to_string(),
+                span.end_line.to_string(),
+                span.end_col.to_string(),
+            ),
+            None => {
+                let null = String::from("null");
+                (null.clone(), null.clone(), null.clone(), null)
+            },
+        };
+
+        format!(
+            "{{\"file\":{},\"start_line\":{},\"start_col\":{},\"end_line\":{},\"end_col\":{},\"severity\":\"{}\",\"phase\":\"{}\",\"code\":\"{}\",\"message\":{}}}",
+            json_string(file_name),
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+            self.severity,
+            self.phase,
+            self.code,
+            json_string(&self.message),
+        )
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_primary_span() {
+        let source = "var a = 1;\nprint a +;\n";
+        let diagnostic = Diagnostic::error(Phase::Parse, "E0102", String::from("Expected expression."))
+            .with_span(Span { line: 2, col: 10, end_line: 2, end_col: 11 })
+            .with_label(String::from("found `;`"));
+
+        assert_eq!(
+            diagnostic.render("test.lox", source),
+            "error[E0102]: Expected expression.\n \
+             --> test.lox:2:10\n  \
+             |\n\
+             2 | print a +;\n  \
+             |          ^ found `;`\n",
+        );
+    }
+
+    #[test]
+    fn render_secondary_labels_and_notes() {
+        let source = "{\n  var count = 1;\n  var count = 2;\n}\n";
+        let diagnostic = Diagnostic::error(Phase::Resolve, "E0202", String::from("Already a variable with this name in this scope."))
+            .with_span(Span { line: 3, col: 7, end_line: 3, end_col: 12 })
+            .with_secondary(Span { line: 2, col: 7, end_line: 2, end_col: 12 }, String::from("first declared here"))
+            .with_note(String::from("use assignment to change the value"));
+
+        assert_eq!(
+            diagnostic.render("test.lox", source),
+            "error[E0202]: Already a variable with this name in this scope.\n \
+             --> test.lox:3:7\n  \
+             |\n\
+             2 |   var count = 1;\n  \
+             |       ----- first declared here\n\
+             3 |   var count = 2;\n  \
+             |       ^^^^^\n  \
+             |\n  \
+             = note: use assignment to change the value\n",
+        );
+    }
+
+    #[test]
+    fn json_output() {
+        let diagnostic = Diagnostic::error(Phase::Parse, "E0101", String::from("Expect ';' after \"value\"."))
+            .with_span(Span { line: 2, col: 5, end_line: 2, end_col: 8 });
+
+        assert_eq!(
+            diagnostic.to_json("dir\\test.lox"),
+            "{\"file\":\"dir\\\\test.lox\",\"start_line\":2,\"start_col\":5,\"end_line\":2,\"end_col\":8,\
+             \"severity\":\"error\",\"phase\":\"parse\",\"code\":\"E0101\",\"message\":\"Expect ';' after \\\"value\\\".\"}",
+        );
+
+        let diagnostic = Diagnostic::error(Phase::Runtime, "E0302", String::from("tab\there"));
+
+        assert_eq!(
+            diagnostic.to_json("<eval>"),
+            "{\"file\":\"<eval>\",\"start_line\":null,\"start_col\":null,\"end_line\":null,\"end_col\":null,\
+             \"severity\":\"error\",\"phase\":\"runtime\",\"code\":\"E0302\",\"message\":\"tab\\there\"}",
+        );
+    }
+
+    #[test]
+    fn span_covering() {
+        assert_eq!(Span::covering(2, 5, "abc"), Span { line: 2, col: 5, end_line: 2, end_col: 8 });
+        assert_eq!(Span::covering(2, 5, ""), Span { line: 2, col: 5, end_line: 2, end_col: 6 });
+        assert_eq!(Span::covering(2, 5, "\"a\nbc\ndéf\n"), Span { line: 2, col: 5, end_line: 4, end_col: 4 });
+    }
+
+    #[test]
+    fn render_without_span() {
+        let diagnostic = Diagnostic::error(Phase::Runtime, "E0301", String::from("Stack overflow."))
+            .with_note(String::from("raised by the interpreter"));
+
+        assert_eq!(
+            diagnostic.render("test.lox", ""),
+            "error[E0301]: Stack overflow.\n  = note: raised by the interpreter\n",
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use crate::scanner::token::Token;
+use crate::value::LValue;
+use crate::runner::error::RunTimeError;
+use super::Environment;
+
+/// Interned name of a global variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+pub struct Symbol(usize);
+
+/// Global variables, indexed by the symbol of their name. A symbol may be
+/// interned by the resolver long before the variable gets defined.
+#[derive(Debug, Clone, Default)]
+pub struct Globals {
+    symbols: HashMap<String, Symbol>,
+    names: Vec<String>,
+    values: Vec<Option<LValue>>,
+}
+
+impl Globals {
+    pub fn new() -> Globals {
+        Globals::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.names.len());
+        self.symbols.insert(name.to_string(), symbol);
+        self.names.push(name.to_string());
+        self.values.push(None);
+        symbol
+    }
+
+    pub fn intern_token(&mut self, token: &Token) -> Symbol {
+        self.intern(&String::from_utf8_lossy(&token.lexeme))
+    }
+
+    pub fn define(&mut self, symbol: Symbol, value: LValue) {
+        self.values[symbol.0] = Some(value);
+    }
+
+    pub fn get(&self, symbol: Symbol, token: &Token) -> Result<LValue, RunTimeError> {
+        match self.values.get(symbol.0) {
+            Some(Some(value)) => Ok(value.clone()),
+            _ => Err(Environment::undefined(token)),
+        }
+    }
+
+    pub fn assign(&mut self, symbol: Symbol, token: &Token, value: LValue) -> Result<(), RunTimeError> {
+        match self.values.get_mut(symbol.0) {
+            Some(Some(variable)) => {
+                *variable = value;
+                Ok(())
+            },
+            _ => Err(Environment::undefined(token)),
+        }
+    }
+
+    /// Defined globals with their names
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LValue)> {
+        self.names
+            .iter()
+            .zip(self.values.iter())
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name.as_str(), value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::token::TokenType;
+
+    #[test]
+    fn intern_and_define() {
+        let token = Token {
+            typee: TokenType::Identifier,
+            lexeme: String::from("a").into_bytes(),
+            literal: None,
+            line: 1,
+            col: 1,
+        };
+
+        let mut globals = Globals::new();
+        let symbol = globals.intern_token(&token);
+
+        assert_eq!(globals.intern("a"), symbol);
+        assert_ne!(globals.intern("b"), symbol);
+        assert!(globals.get(symbol, &token).is_err());
+        assert!(globals.assign(symbol, &token, LValue::Nil).is_err());
+
+        globals.define(symbol, LValue::Number(1.0));
+        globals.assign(symbol, &token, LValue::Number(2.0)).unwrap();
+
+        assert_eq!(globals.get(symbol, &token).unwrap(), LValue::Number(2.0));
+        assert_eq!(globals.iter().collect::<Vec<_>>(), vec![("a", &LValue::Number(2.0))]);
+    }
+}
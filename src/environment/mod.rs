@@ -1,108 +1,80 @@
-use std::cell::{RefCell, RefMut};
-use std::collections::HashMap;
-use std::ops::Deref;
-use std::ptr;
+use std::cell::RefCell;
 use std::rc::Rc;
 use crate::scanner::token::Token;
 use crate::value::LValue;
 use crate::runner::error::{CommonError, RunTimeError};
 
+pub mod globals;
+
+/// Local variables of one scope, stored in the order they are declared.
+/// The resolver gives every local the same slot it gets here.
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub enclosing: Option<Rc<RefCell<Environment>>>,
-    map: HashMap<String, LValue>,    
+    values: Vec<LValue>,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
-            map: HashMap::new(),
+            values: Vec::new(),
             enclosing: None,
         }
     }
 
-    pub fn define(&mut self, name: String, value: LValue) {
-        self.map.insert(name, value);
-    }
-
-    pub fn assign(&mut self, token: &Token, value: LValue) -> Result<(), RunTimeError>{
-        let name_string = String::from_utf8(token.lexeme.to_vec()).unwrap();
-
-        if self.map.contains_key(&name_string) {
-            self.map.insert(name_string, value);
-            return Ok(());
-        }
-
-        if let Some(ref enclosing) = self.enclosing {
-            return enclosing.borrow_mut().assign(token, value);
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: Vec::new(),
+            enclosing: Some(enclosing),
         }
+    }
 
-        // if let Some(env) = &mut self.enclosing {
-        //     return env.assign(token, value);
-        // }
+    /// Whether this is the top level scope, whose variables are globals
+    pub fn is_root(&self) -> bool {
+        self.enclosing.is_none()
+    }
 
-        Err(RunTimeError::Error(CommonError {
-            token: Some(token.clone()),
-            message: "Undefined variable".to_owned() + name_string.as_str(),
-        }))
+    /// Define the next local and return its slot
+    pub fn define(&mut self, value: LValue) -> usize {
+        self.values.push(value);
+        self.values.len() - 1
     }
 
-    pub fn get(&self, token: &Token) -> Result<LValue, RunTimeError>{
-        let name_string = String::from_utf8(token.lexeme.to_vec()).unwrap();
-        
-        if self.map.contains_key(&name_string) {
-            return Ok(self.map.get(&name_string).unwrap().clone());
+    pub fn get_at(&self, depth: usize, slot: usize, token: &Token) -> Result<LValue, RunTimeError> {
+        if depth == 0 {
+            return match self.values.get(slot) {
+                Some(value) => Ok(value.clone()),
+                None => Err(Environment::undefined(token)),
+            };
         }
 
-        if let Some(ref enclosing) = self.enclosing {
-            return enclosing.borrow_mut().get(token);
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.borrow().get_at(depth - 1, slot, token),
+            None => Err(Environment::undefined(token)),
         }
-        
-        // if let Some(env) = &self.enclosing {
-        //     return env.get(token);
-        // }
-
-        Err(RunTimeError::Error(CommonError {
-            token: Some(token.clone()),
-            message: "Undefined variable".to_owned() + name_string.as_str(),
-        }))
     }
 
-    pub fn get_at(&self, distance: usize, token: &Token) -> Result<LValue, RunTimeError> {
-        let name_string = String::from_utf8(token.lexeme.to_vec()).unwrap();
-
-        if distance == 0 {
-            return Ok(self.map.get(&name_string).unwrap().clone());
+    pub fn assign_at(&mut self, depth: usize, slot: usize, token: &Token, value: LValue) -> Result<(), RunTimeError> {
+        if depth == 0 {
+            return match self.values.get_mut(slot) {
+                Some(variable) => {
+                    *variable = value;
+                    Ok(())
+                },
+                None => Err(Environment::undefined(token)),
+            };
         }
 
-        let mut ancestor_env = self.enclosing.clone().unwrap();
-        
-        for _ in 1..distance {
-            let clone_env_option = ancestor_env.borrow().enclosing.clone();
-            ancestor_env = clone_env_option.unwrap();
+        match self.enclosing {
+            Some(ref enclosing) => enclosing.borrow_mut().assign_at(depth - 1, slot, token, value),
+            None => Err(Environment::undefined(token)),
         }
-
-        let res = ancestor_env.borrow_mut().map.get(&name_string).unwrap().clone();
-        Ok(res)
     }
 
-    pub fn assign_at(&mut self, distance: usize, token: &Token, value: LValue) -> Result<(), RunTimeError> {
-        let name_string = String::from_utf8(token.lexeme.to_vec()).unwrap();
-        
-        if distance == 0 {
-            self.map.insert(name_string, value);
-            return Ok(());
-        }
-
-        let mut ancestor_env = self.enclosing.clone().unwrap();
-
-        for _ in 1..distance {
-            let clone_env_option = ancestor_env.borrow().enclosing.clone();
-            ancestor_env = clone_env_option.unwrap();
-        }       
-
-        ancestor_env.borrow_mut().map.insert(name_string, value);
-
-        Ok(())
+    pub fn undefined(token: &Token) -> RunTimeError {
+        RunTimeError::Error(CommonError {
+            token: Some(token.clone()),
+            message: format!("Undefined variable '{}'.", String::from_utf8_lossy(&token.lexeme)),
+        })
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,7 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use crate::environment::globals::Symbol;
+use crate::grammer::statement::Stmt;
 use crate::scanner::token::Token;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -6,15 +10,38 @@ pub enum Expr {
     Unary(Token, Box<Expr>),
     Binary(Box<Expr>, Token, Box<Expr>),
     Literal(ExprLiteral),
-    Variable(Token),
-    Assign(Token, Box<Expr>),
+    Variable(Token, Cell<Resolution>),
+    Assign(Token, Box<Expr>, Cell<Resolution>),
     Logical(Box<Expr>, Token, Box<Expr>),
     Call(Box<Expr>, Token, Vec<Expr>),
     New(Token, Box<Expr>),
     Get(Box<Expr>, Token),
     Set(Box<Expr>, Token, Box<Expr>),
-    This(Token),
-    Super(Token, Box<Expr>),
+    This(Token, Cell<Resolution>),
+    Super(Token, Box<Expr>, Cell<Resolution>),
+    // Anonymous function, named after its `lambda` keyword
+    Lambda(Token, Rc<Vec<Token>>, Rc<Stmt>),
+    // Tokens of list expressions are their opening bracket
+    List(Token, Vec<Expr>),
+    // Opening brace and key value pairs
+    Map(Token, Vec<(Expr, Expr)>),
+    // First segment of the string and its parts in order, each displayed
+    // like `print` does
+    Interpolation(Token, Vec<Expr>),
+    Index(Box<Expr>, Token, Box<Expr>),
+    SetIndex(Box<Expr>, Token, Box<Expr>, Box<Expr>),
+}
+
+/// Where the variable an expression refers to lives, filled in by the resolver
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub enum Resolution {
+    #[default]
+    Unresolved,
+    Local {
+        depth: usize,
+        slot: usize,
+    },
+    Global(Symbol),
 }
 
 pub trait ExprVistor<T> {
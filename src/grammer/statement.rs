@@ -12,10 +12,17 @@ pub enum Stmt {
     Var(Token, Option<Expr>),
     Block(Vec<Stmt>),
     If(Expr, Box<Stmt>, Box<Option<Stmt>>),
-    While(Expr, Box<Stmt>),
-    Function(Token, Vec<Token>, Box<Stmt>),
+    // Condition, body and the increment of a desugared `for`, which runs
+    // after the body even when it continues
+    While(Expr, Box<Stmt>, Option<Expr>),
+    Function(Token, Rc<Vec<Token>>, Rc<Stmt>),
     Class(Token, Option<Expr>, Vec<Stmt>),
     Return(Token, Option<Expr>),
+    Break(Token),
+    Continue(Token),
+    Throw(Token, Expr),
+    // Body, the catch clause with its variable, and the finally clause
+    Try(Box<Stmt>, Option<(Token, Box<Stmt>)>, Option<Box<Stmt>>),
 }
 
 #[derive(Debug, Clone, Copy)]
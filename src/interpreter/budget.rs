@@ -0,0 +1,157 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use crate::runner::error::RunTimeError;
+use crate::scanner::token::Token;
+
+// Deadline checks between two readings of the clock
+const CHECKS_PER_CLOCK_READ: u32 = 64;
+
+/// Why a run was stopped before it completed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Abort {
+    // Limit of steps that was exceeded
+    Steps(u64),
+    // Timeout that elapsed
+    Timeout(Duration),
+    Cancelled,
+}
+
+impl fmt::Display for Abort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Abort::Steps(max_steps) => write!(f, "Execution budget exceeded: more than {} steps.", max_steps),
+            Abort::Timeout(timeout) => write!(f, "Execution budget exceeded: timed out after {} ms.", timeout.as_millis()),
+            Abort::Cancelled => write!(f, "Execution cancelled."),
+        }
+    }
+}
+
+/// Stops the runs of the interpreter it was taken from, from any thread
+/// or signal handler, as the prompt does on Ctrl-C.
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Limits put on each run of a program. Steps are the statements and
+/// expressions the tree-walking interpreter evaluates, or the instructions
+/// the virtual machine executes.
+pub struct Budget {
+    pub max_steps: Option<u64>,
+    pub timeout: Option<Duration>,
+    steps: u64,
+    // Instant the current run times out at, with the timeout it came from
+    deadline: Option<(Instant, Duration)>,
+    checks: u32,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Budget {
+    pub fn new() -> Budget {
+        Budget {
+            max_steps: None,
+            timeout: None,
+            steps: 0,
+            deadline: None,
+            checks: 0,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Start counting for a new run, once cancelled every run is
+    pub fn start(&mut self) {
+        self.steps = 0;
+        self.checks = 0;
+        self.deadline = self.timeout.map(|timeout| (Instant::now() + timeout, timeout));
+    }
+
+    /// Let runs go on again after a cancellation
+    pub fn resume(&mut self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            cancelled: self.cancelled.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn step(&mut self) -> Result<(), RunTimeError> {
+        if let Some(max_steps) = self.max_steps {
+            self.steps += 1;
+
+            if self.steps > max_steps {
+                return Err(RunTimeError::Aborted(Abort::Steps(max_steps), None));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Look for a cancellation or a passed deadline, done at loop
+    /// back-edges and calls so that no run can avoid it for long
+    pub fn check(&mut self, token: Option<&Token>) -> Result<(), RunTimeError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(RunTimeError::Aborted(Abort::Cancelled, token.cloned()));
+        }
+
+        if let Some((deadline, timeout)) = self.deadline {
+            self.checks += 1;
+
+            if self.checks >= CHECKS_PER_CLOCK_READ {
+                self.checks = 0;
+
+                if Instant::now() >= deadline {
+                    return Err(RunTimeError::Aborted(Abort::Timeout(timeout), token.cloned()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_limits() {
+        let mut budget = Budget::new();
+        budget.max_steps = Some(2);
+        budget.start();
+
+        assert!(budget.step().is_ok());
+        assert!(budget.step().is_ok());
+        assert_eq!(budget.step(), Err(RunTimeError::Aborted(Abort::Steps(2), None)));
+
+        budget.start();
+        assert!(budget.step().is_ok());
+
+        budget.timeout = Some(Duration::ZERO);
+        budget.start();
+        let checks = (0..CHECKS_PER_CLOCK_READ).map(|_| budget.check(None)).collect::<Vec<_>>();
+        assert!(checks[..checks.len() - 1].iter().all(|check| check.is_ok()));
+        assert_eq!(checks.last(), Some(&Err(RunTimeError::Aborted(Abort::Timeout(Duration::ZERO), None))));
+
+        budget.timeout = None;
+        budget.start();
+        let handle = budget.cancel_handle();
+        std::thread::spawn(move || handle.cancel()).join().unwrap();
+        assert_eq!(budget.check(None), Err(RunTimeError::Aborted(Abort::Cancelled, None)));
+
+        budget.start();
+        assert!(budget.check(None).is_err());
+        budget.resume();
+        assert_eq!(budget.check(None), Ok(()));
+    }
+}
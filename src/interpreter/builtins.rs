@@ -0,0 +1,113 @@
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::runner::error::{CommonError, RunTimeError};
+use crate::value::LValue;
+use crate::value::list::List;
+use crate::value::map::Map;
+use super::Interpreter;
+
+/// Define every builtin function in the globals
+pub fn define_builtins(interpreter: &mut Interpreter) {
+    interpreter.define_native("clock", 0, clock);
+    interpreter.define_native("len", 1, len);
+    interpreter.define_native("push", 2, push);
+    interpreter.define_native("pop", 1, pop);
+    interpreter.define_native("insert", 3, insert);
+    interpreter.define_native("remove", 2, remove);
+    interpreter.define_native("keys", 1, keys);
+    interpreter.define_native("values", 1, values);
+    interpreter.define_native("has", 2, has);
+    interpreter.define_native("delete", 2, delete);
+}
+
+// Errors of natives get pointed at the call by the caller
+fn error(message: String) -> RunTimeError {
+    RunTimeError::Error(CommonError {
+        token: None,
+        message,
+    })
+}
+
+fn expect_list(name: &str, value: &LValue) -> Result<Rc<List>, RunTimeError> {
+    match value {
+        LValue::List(list) => Ok(list.clone()),
+        _ => Err(error(format!("{} expects a list, got {}", name, value.kind()))),
+    }
+}
+
+fn expect_map(name: &str, value: &LValue) -> Result<Rc<Map>, RunTimeError> {
+    match value {
+        LValue::Map(map) => Ok(map.clone()),
+        _ => Err(error(format!("{} expects a map, got {}", name, value.kind()))),
+    }
+}
+
+/// Seconds since the Unix epoch, for timing scripts
+fn clock(_interpreter: &mut Interpreter, _arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => Ok(LValue::Number(duration.as_secs_f64())),
+        Err(_) => Err(RunTimeError::Error(CommonError {
+            token: None,
+            message: String::from("System clock is set before the Unix epoch."),
+        })),
+    }
+}
+
+/// Number of elements of a list or map, or of characters of a string
+fn len(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    match &arguments[0] {
+        LValue::List(list) => Ok(LValue::Number(list.len() as f64)),
+        LValue::Map(map) => Ok(LValue::Number(map.len() as f64)),
+        LValue::String(string) => Ok(LValue::Number(string.chars().count() as f64)),
+        value => Err(error(format!("len expects a list, a map or a string, got {}", value.kind()))),
+    }
+}
+
+fn push(_interpreter: &mut Interpreter, mut arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let list = expect_list("push", &arguments[0])?;
+    list.push(arguments.remove(1));
+    Ok(LValue::Nil)
+}
+
+/// Remove and return the last element
+fn pop(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let list = expect_list("pop", &arguments[0])?;
+    list.pop().ok_or_else(|| error(String::from("Can't pop from an empty list.")))
+}
+
+/// Insert an element before an index, which may be the length to append
+fn insert(_interpreter: &mut Interpreter, mut arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let list = expect_list("insert", &arguments[0])?;
+    let value = arguments.remove(2);
+    list.insert(&arguments[1], value).map_err(error)?;
+    Ok(LValue::Nil)
+}
+
+/// Remove and return the element at an index
+fn remove(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let list = expect_list("remove", &arguments[0])?;
+    list.remove(&arguments[1]).map_err(error)
+}
+
+/// Keys of a map as a new list, in insertion order
+fn keys(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let map = expect_map("keys", &arguments[0])?;
+    Ok(LValue::List(List::new(map.keys())))
+}
+
+/// Values of a map as a new list, in insertion order
+fn values(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let map = expect_map("values", &arguments[0])?;
+    Ok(LValue::List(List::new(map.values())))
+}
+
+fn has(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let map = expect_map("has", &arguments[0])?;
+    map.has(&arguments[1]).map(LValue::Bool).map_err(error)
+}
+
+/// Remove a key from a map, returning whether it was there
+fn delete(_interpreter: &mut Interpreter, arguments: Vec<LValue>) -> Result<LValue, RunTimeError> {
+    let map = expect_map("delete", &arguments[0])?;
+    map.delete(&arguments[1]).map(LValue::Bool).map_err(error)
+}
@@ -1,39 +1,121 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::rc::Rc;
 use crate::environment::Environment;
+use crate::environment::globals::Globals;
 use crate::grammer::statement::Stmt;
-use crate::runner::error::RunTimeError;
+use crate::runner::error::{CommonError, RunTimeError};
 use crate::scanner::token::Token;
+use crate::value::LValue;
+use crate::value::native::NativeFunction;
+use budget::Budget;
+use output::{Output, Stdout};
+use trace::{CallSite, TraceFrame};
+
+pub mod budget;
+pub mod builtins;
+pub mod output;
+pub mod trace;
+#[cfg(test)]
+pub mod testing;
+
+/// Calls that can be nested by default
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+// Native stack a call of the tree-walking interpreter may take, with room
+// for bodies nesting statements and expressions a few levels deep. Frames
+// are several times larger without optimizations.
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) { 320 * 1024 } else { 64 * 1024 };
+// Native stack used outside of calls, by parsing and resolving among others
+const STACK_BASE: usize = 8 * 1024 * 1024;
 
 pub struct Interpreter {
-    pub globals: Rc<RefCell<Environment>>,
+    pub globals: Globals,
+    // Innermost local scope, the root one stands for the globals
     pub environment: Rc<RefCell<Environment>>,   
-    pub locals: HashMap<Token, usize>,
+    // Functions being run, innermost last
+    pub calls: Vec<CallSite>,
+    // Frames left by the last error to escape, until it is reported
+    pub traceback: Option<Vec<TraceFrame>>,
+    // Calls deeper than this raise a stack overflow error
+    pub max_call_depth: usize,
+    // Steps and time each run may take
+    pub budget: Budget,
+    // Where `print` writes
+    pub output: Box<dyn Output>,
 }
 
 impl Interpreter {
+    /// Bytes of native stack to give the thread running programs that nest
+    /// up to `max_call_depth` calls
+    pub fn stack_size(max_call_depth: usize) -> usize {
+        max_call_depth.saturating_mul(STACK_PER_CALL).saturating_add(STACK_BASE)
+    }
+
     pub fn new() -> Interpreter {
-        // TODO：add native function clock
-        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Interpreter{
+            globals: Globals::new(),
+            environment: Rc::new(RefCell::new(Environment::new())),
+            calls: Vec::new(),
+            traceback: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            budget: Budget::new(),
+            output: Box::new(Stdout),
+        };
 
-        Interpreter{
-            globals: globals.clone(),
-            environment: globals.clone(),
-            locals: HashMap::new(),
-        }
+        builtins::define_builtins(&mut interpreter);
+        interpreter
+    }
+
+    /// Define a function implemented in Rust as a global, calls with a
+    /// different number of arguments than `arity` fail before reaching it
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&mut Interpreter, Vec<LValue>) -> Result<LValue, RunTimeError> + 'static,
+    {
+        let native = NativeFunction::new(name, arity, function);
+        let symbol = self.globals.intern(name);
+        self.globals.define(symbol, LValue::NativeFunction(Rc::new(native)));
     }
 
     pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), RunTimeError> {
+        self.budget.start();
+
         for statement in statements {
-            let res = self.exectue(&statement)?;
+            if let Err(error) = self.exectue(&statement) {
+                self.record_traceback(&error);
+                return Err(error);
+            }
         }
 
         Ok(())
     }
 
-    pub fn resolve(&mut self, token: &Token, depth: usize) {
-        self.locals.insert(token.clone(), depth);
+    /// Error raised instead of calling `function` once `max_call_depth`
+    /// calls are nested, pointing at the call
+    pub fn stack_overflow(&self, function: &str, token: &Token) -> RunTimeError {
+        RunTimeError::Error(CommonError {
+            message: format!("Stack overflow in {}: more than {} nested calls.", function, self.max_call_depth),
+            token: Some(token.clone()),
+        })
+    }
+
+    /// Write a printed value to the output
+    pub fn print(&mut self, value: &LValue) -> Result<(), RunTimeError> {
+        self.output.write_line(&value.to_string()).map_err(|error| RunTimeError::Error(CommonError {
+            message: format!("Could not print: {}", error),
+            token: None,
+        }))
+    }
+
+    /// Define a variable in the current scope, in the same slot the resolver
+    /// gave it, or as a global at the top level
+    pub fn define_variable(&mut self, name: &Token, value: LValue) {
+        if self.environment.borrow().is_root() {
+            let symbol = self.globals.intern_token(name);
+            self.globals.define(symbol, value);
+        } else {
+            self.environment.borrow_mut().define(value);
+        }
     }
 }
 
@@ -41,9 +123,429 @@ impl Interpreter {
 mod tests {
     use super::*;
     use crate::resolver::Resolver;
+    use std::time::Duration;
+    use crate::interpreter::budget::Abort;
+    use crate::interpreter::output::Captured;
+    use crate::runner::error::CommonError;
     use crate::scanner::Scanner;
-    use crate::scanner::token::Token;
+    use crate::scanner::token::{Token, TokenType};
     use crate::parser::Parser;
+    use crate::runner::options::Engine;
+    use super::testing::{self, global};
+
+    const ENGINES: [Engine; 2] = [Engine::Tree, Engine::Vm];
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), RunTimeError> {
+        testing::run(Engine::Tree, interpreter, source)
+    }
+
+    #[test]
+    fn native_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_native("add", 2, |_, arguments| {
+            match (&arguments[0], &arguments[1]) {
+                (LValue::Number(a), LValue::Number(b)) => Ok(LValue::Number(a + b)),
+                _ => Err(RunTimeError::Error(CommonError {
+                    token: None,
+                    message: String::from("add expects numbers"),
+                })),
+            }
+        });
+
+        run(&mut interpreter, "var start = clock(); var sum = add(1, 2); var elapsed = clock() - start;").unwrap();
+
+        assert_eq!(global(&interpreter, "sum"), LValue::Number(3.0));
+        assert!(matches!(global(&interpreter, "start"), LValue::Number(seconds) if seconds > 0.0));
+        assert!(matches!(global(&interpreter, "elapsed"), LValue::Number(seconds) if seconds >= 0.0));
+
+        let error = run(&mut interpreter, "add(1);").unwrap_err();
+        assert!(matches!(error, RunTimeError::Error(CommonError { message, .. }) if message == "Expected 2 arguments but got 1."));
+
+        let error = run(&mut interpreter, "add(1, \"a\");").unwrap_err();
+        assert!(matches!(error, RunTimeError::Error(CommonError { message, .. }) if message == "add expects numbers"));
+    }
+
+    #[test]
+    fn call_arity() {
+        let definitions = "
+            fun add(a, b) { return a + b; }
+            class Empty {}
+            class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                move(dx) { this.x = this.x + dx; }
+            }
+            class Pixel extend Point {}
+        ";
+
+        let source_expected: Vec<(&str, usize, usize)> = vec![
+            ("add(1);", 2, 1),
+            ("add(1, 2, 3);", 2, 3),
+            ("new Empty(1);", 0, 1),
+            ("new Point(1);", 2, 1),
+            ("new Pixel();", 2, 0),
+            ("var p = new Point(1, 2);\np.move();", 1, 0),
+            ("clock(1);", 0, 1),
+        ];
+
+        for (source, arity, count) in source_expected {
+            let mut interpreter = Interpreter::new();
+            run(&mut interpreter, definitions).unwrap();
+
+            match run(&mut interpreter, source).unwrap_err() {
+                RunTimeError::Error(CommonError { message, token }) => {
+                    assert_eq!(message, format!("Expected {} arguments but got {}.", arity, count));
+                    assert_eq!(token.unwrap().typee, TokenType::RightParen);
+                },
+                error => panic!("Unexpected error {:?}", error),
+            }
+        }
+
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, definitions).unwrap();
+        run(&mut interpreter, "var sum = add(1, 2); var p = new Pixel(3, 4); p.move(1); var x = p.x;").unwrap();
+
+        assert_eq!(global(&interpreter, "sum"), LValue::Number(3.0));
+        assert_eq!(global(&interpreter, "x"), LValue::Number(4.0));
+    }
+
+    #[test]
+    fn local_slots() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            fun makeCounter() {
+                var i = 0;
+                fun count() { i = i + 1; return i; }
+                return count;
+            }
+            var counter = makeCounter();
+            counter();
+            var counted = counter();
+
+            var shadowed;
+            {
+                var a = \"outer\";
+                {
+                    fun show() { return a; }
+                    var a = \"inner\";
+                    shadowed = show() + a;
+                }
+            }
+
+            var local;
+            {
+                var offset = 1;
+                class Point {
+                    init(x) { this.x = x; }
+                    copy() { return new Point(this.x); }
+                }
+                class Pixel extend Point {
+                    copy() { return super.copy().x + offset; }
+                }
+                var copied = new Pixel(1).copy();
+                local = copied;
+            }
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "counted"), LValue::Number(2.0));
+        assert_eq!(global(&interpreter, "shadowed"), LValue::String(String::from("outerinner")));
+        assert_eq!(global(&interpreter, "local"), LValue::Number(2.0));
+    }
+
+    #[test]
+    fn logical_short_circuit() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var calls = 0;
+            fun touch(value) { calls = calls + 1; return value; }
+
+            var skipped_or = touch(\"left\") or touch(\"right\");
+            var skipped_and = touch(nil) and touch(1);
+            var after_skips = calls;
+
+            var both_or = touch(false) or touch(\"right\");
+            var both_and = touch(1) and touch(2);
+            var name = nil;
+            var fallback = name or \"default\";
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "skipped_or"), LValue::String(String::from("left")));
+        assert_eq!(global(&interpreter, "skipped_and"), LValue::Nil);
+        assert_eq!(global(&interpreter, "after_skips"), LValue::Number(2.0));
+        assert_eq!(global(&interpreter, "both_or"), LValue::String(String::from("right")));
+        assert_eq!(global(&interpreter, "both_and"), LValue::Number(2.0));
+        assert_eq!(global(&interpreter, "calls"), LValue::Number(6.0));
+        assert_eq!(global(&interpreter, "fallback"), LValue::String(String::from("default")));
+    }
+
+    #[test]
+    fn lambda_closures() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            fun apply(f, value) { return f(value); }
+            var doubled = apply(lambda (n) { return n * 2; }, 4);
+            var arrow = apply(lambda (n) => n + 1, 4);
+
+            var adders;
+            {
+                var base = 10;
+                adders = lambda (n) => lambda (x) => x + n + base;
+            }
+            var added = adders(5)(1);
+
+            class Box {
+                init(value) { this.value = value; }
+                getter() { return lambda () => this.value; }
+            }
+            var boxed = Box(3).getter()();
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "doubled"), LValue::Number(8.0));
+        assert_eq!(global(&interpreter, "arrow"), LValue::Number(5.0));
+        assert_eq!(global(&interpreter, "added"), LValue::Number(16.0));
+        assert_eq!(global(&interpreter, "boxed"), LValue::Number(3.0));
+    }
+
+    #[test]
+    fn loop_control() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var visited = 0;
+            var steps = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                steps = steps + 1;
+                if (i < 3) continue;
+                if (i == 6) break;
+                visited = visited + i;
+            }
+
+            var outer = 0;
+            while (outer < 3) {
+                outer = outer + 1;
+                for (;;) {
+                    { var nested = outer; if (nested > 0) break; }
+                }
+                if (outer == 2) break;
+            }
+
+            fun first(xs) {
+                for (var i = 0; i < len(xs); i = i + 1) {
+                    if (xs[i] > 1) return xs[i];
+                }
+            }
+            var found = first([1, 2, 3]);
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "visited"), LValue::Number(12.0));
+        assert_eq!(global(&interpreter, "steps"), LValue::Number(7.0));
+        assert_eq!(global(&interpreter, "outer"), LValue::Number(2.0));
+        assert_eq!(global(&interpreter, "found"), LValue::Number(2.0));
+    }
+
+    #[test]
+    fn exceptions() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var message;
+            var line;
+            try {
+                var a = 1;
+                a();
+            } catch (e) {
+                message = e.message;
+                line = e.line;
+            }
+
+            var thrown;
+            var cleaned = false;
+            try {
+                try { throw [1]; } finally { cleaned = true; }
+            } catch (e) {
+                thrown = e[0];
+            }
+
+            fun early() {
+                try { return \"try\"; } finally { cleaned = \"early\"; }
+            }
+            var returned = early();
+
+            var e = \"outer\";
+            try { throw 1; } catch (e) { e = 2; }
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "message"), LValue::String(String::from("Can only call functions and classes.")));
+        assert_eq!(global(&interpreter, "line"), LValue::Number(6.0));
+        assert_eq!(global(&interpreter, "thrown"), LValue::Number(1.0));
+        assert_eq!(global(&interpreter, "returned"), LValue::String(String::from("try")));
+        assert_eq!(global(&interpreter, "cleaned"), LValue::String(String::from("early")));
+        assert_eq!(global(&interpreter, "e"), LValue::String(String::from("outer")));
+        assert!(interpreter.environment.borrow().is_root());
+
+        match run(&mut interpreter, "try { throw \"again\"; } finally { var f = 1; }").unwrap_err() {
+            RunTimeError::Throw(value, token) => {
+                assert_eq!(value, LValue::String(String::from("again")));
+                assert_eq!(token.typee, TokenType::Throw);
+            },
+            error => panic!("Unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn stack_overflow() {
+        for engine in ENGINES {
+            let mut interpreter = Interpreter::new();
+            interpreter.max_call_depth = 20;
+            testing::run(engine, &mut interpreter, "
+                fun r(n) { return r(n + 1); }
+                var message;
+                try { r(0); } catch (e) { message = e.message; }
+
+                fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); }
+                var deepest = count(19);
+            ").unwrap();
+
+            assert_eq!(global(&interpreter, "message"), LValue::String(String::from("Stack overflow in r: more than 20 nested calls.")));
+            assert_eq!(global(&interpreter, "deepest"), LValue::Number(19.0));
+            assert!(interpreter.calls.is_empty());
+
+            match testing::run(engine, &mut interpreter, "class A { m() { return this.m(); } } A().m();").unwrap_err() {
+                RunTimeError::Error(error) => assert_eq!(error.message, "Stack overflow in A.m: more than 20 nested calls."),
+                error => panic!("Unexpected error {:?}", error),
+            }
+            assert!(interpreter.calls.is_empty());
+        }
+    }
+
+    #[test]
+    fn execution_budget() {
+        for engine in ENGINES {
+            let mut interpreter = Interpreter::new();
+            interpreter.budget.max_steps = Some(1000);
+            testing::run(engine, &mut interpreter, "var cleaned = false;").unwrap();
+
+            let error = testing::run(engine, &mut interpreter, "try { while (true) {} } catch (e) {} finally { cleaned = true; }").unwrap_err();
+            assert_eq!(error, RunTimeError::Aborted(Abort::Steps(1000), None));
+            assert_eq!(global(&interpreter, "cleaned"), LValue::Bool(false));
+
+            // Each run starts with the whole budget
+            testing::run(engine, &mut interpreter, "for (var i = 0; i < 10; i = i + 1) {}").unwrap();
+
+            interpreter.budget.max_steps = None;
+            interpreter.budget.timeout = Some(Duration::from_millis(10));
+            match testing::run(engine, &mut interpreter, "fun spin() { while (true) {} } spin();").unwrap_err() {
+                RunTimeError::Aborted(abort, _) => assert_eq!(abort, Abort::Timeout(Duration::from_millis(10))),
+                error => panic!("Unexpected error {:?}", error),
+            }
+            assert!(interpreter.calls.is_empty());
+
+            interpreter.budget.timeout = None;
+            interpreter.budget.cancel_handle().cancel();
+            match testing::run(engine, &mut interpreter, "fun f() {} f();").unwrap_err() {
+                RunTimeError::Aborted(abort, token) => {
+                    assert_eq!(abort, Abort::Cancelled);
+                    assert_eq!(token.unwrap().typee, TokenType::RightParen);
+                },
+                error => panic!("Unexpected error {:?}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn print_to_output() {
+        for engine in ENGINES {
+            let output = Captured::default();
+            let mut interpreter = Interpreter::new();
+            interpreter.output = Box::new(output.clone());
+            testing::run(engine, &mut interpreter, "
+                class Point {}
+                print 1 + 2;
+                print \"a\" + \"b\";
+                print Point();
+                print [1, \"x\"];
+            ").unwrap();
+
+            assert_eq!(output.lines(), vec!["3", "ab", "Point instance", "[1, \"x\"]"]);
+        }
+    }
+
+    #[test]
+    fn list_values() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var xs = [1, 2, 3];
+            var alias = xs;
+            alias[0] = 10;
+            push(alias, 4);
+            var popped = pop(xs);
+            insert(xs, 1, 5);
+            var removed = remove(xs, 2);
+            var first = xs[0];
+            var length = len(xs);
+            var text_length = len(\"abc\");
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "popped"), LValue::Number(4.0));
+        assert_eq!(global(&interpreter, "removed"), LValue::Number(2.0));
+        assert_eq!(global(&interpreter, "first"), LValue::Number(10.0));
+        assert_eq!(global(&interpreter, "length"), LValue::Number(3.0));
+        assert_eq!(global(&interpreter, "text_length"), LValue::Number(3.0));
+
+        let source_expected: Vec<(&str, &str, TokenType)> = vec![
+            ("xs[3];", "List index 3 out of bounds for length 3", TokenType::LeftBracket),
+            ("xs[1.5] = 1;", "List index must be an integer, got 1.5", TokenType::LeftBracket),
+            ("first[0];", "Only lists and maps can be indexed.", TokenType::LeftBracket),
+            ("pop([]);", "Can't pop from an empty list.", TokenType::RightParen),
+        ];
+
+        for (source, message, typee) in source_expected {
+            match run(&mut interpreter, source).unwrap_err() {
+                RunTimeError::Error(error) => {
+                    assert_eq!(error.message, message);
+                    assert_eq!(error.token.unwrap().typee, typee);
+                },
+                error => panic!("Unexpected error {:?}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn map_values() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var m = {\"a\": 1, 2: \"two\", nil: false};
+            var alias = m;
+            alias[\"b\"] = 3;
+            var by_number = m[1 + 1];
+            var first_key = keys(m)[0];
+            var last_value = values(m)[3];
+            var deleted = delete(m, \"a\");
+            var present = has(m, \"a\");
+            var length = len(m);
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "by_number"), LValue::String(String::from("two")));
+        assert_eq!(global(&interpreter, "first_key"), LValue::String(String::from("a")));
+        assert_eq!(global(&interpreter, "last_value"), LValue::Number(3.0));
+        assert_eq!(global(&interpreter, "deleted"), LValue::Bool(true));
+        assert_eq!(global(&interpreter, "present"), LValue::Bool(false));
+        assert_eq!(global(&interpreter, "length"), LValue::Number(3.0));
+
+        let source_expected: Vec<(&str, &str, TokenType)> = vec![
+            ("m[\"a\"];", "Key \"a\" not found in map", TokenType::LeftBracket),
+            ("fun f() {} var bad = {f: 1};", "A function can't be used as a map key", TokenType::LeftBrace),
+            ("m[[]] = 1;", "A list can't be used as a map key", TokenType::LeftBracket),
+            ("has([], 1);", "has expects a map, got list", TokenType::RightParen),
+        ];
+
+        for (source, message, typee) in source_expected {
+            match run(&mut interpreter, source).unwrap_err() {
+                RunTimeError::Error(error) => {
+                    assert_eq!(error.message, message);
+                    assert_eq!(error.token.unwrap().typee, typee);
+                },
+                error => panic!("Unexpected error {:?}", error),
+            }
+        }
+    }
 
     #[test]
     fn simple_statement() {
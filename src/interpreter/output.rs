@@ -0,0 +1,37 @@
+use std::io::{self, Write};
+
+/// Where `print` statements write, one call per printed value
+pub trait Output {
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+}
+
+/// Standard output of the process, the default
+pub struct Stdout;
+
+impl Output for Stdout {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{}", line)
+    }
+}
+
+/// Keeps printed lines in memory, clones share them
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct Captured {
+    lines: std::rc::Rc<std::cell::RefCell<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl Captured {
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl Output for Captured {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.lines.borrow_mut().push(line.to_string());
+        Ok(())
+    }
+}
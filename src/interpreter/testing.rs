@@ -0,0 +1,31 @@
+use crate::resolver::Resolver;
+use crate::runner::error::RunTimeError;
+use crate::runner::options::Engine;
+use crate::scanner::Scanner;
+use crate::parser::Parser;
+use crate::value::LValue;
+use crate::vm::Vm;
+use crate::vm::compiler::Compiler;
+use super::Interpreter;
+
+/// Run source code that is known to be valid with either engine
+pub fn run(engine: Engine, interpreter: &mut Interpreter, source: &str) -> Result<(), RunTimeError> {
+    let tokens = Scanner::new(String::from(source)).scan_tokens().unwrap();
+    let statements = Parser::new(tokens).parser().unwrap();
+    Resolver::new(interpreter).resolve(&statements).unwrap();
+
+    match engine {
+        Engine::Tree => interpreter.interpret(statements),
+        Engine::Vm => {
+            let script = Compiler::new(&mut interpreter.globals).compile(&statements).unwrap();
+            Vm::new(interpreter).run(script).map(|_| ())
+        },
+    }
+}
+
+pub fn global(interpreter: &Interpreter, name: &str) -> LValue {
+    interpreter.globals.iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.clone())
+        .unwrap()
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use crate::runner::error::RunTimeError;
+use super::Interpreter;
+
+// Identical frames shown before the rest of a run is summed up
+const REPEATED_FRAMES_SHOWN: usize = 3;
+
+/// Function being called, along with the line it was called from
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    pub function: String,
+    pub line: usize,
+}
+
+/// Frame of a traceback, innermost first
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    // `Class.method` for methods
+    pub function: String,
+    // Line being run in the frame when the error was raised
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "at {} (line {})", self.function, line),
+            None => write!(f, "at {}", self.function),
+        }
+    }
+}
+
+impl TraceFrame {
+    pub fn script(line: Option<usize>) -> TraceFrame {
+        TraceFrame {
+            function: String::from("main script"),
+            line,
+        }
+    }
+
+    /// One line per frame, long runs of the same frame, as left by deep
+    /// recursion, are shortened
+    pub fn render(frames: &[TraceFrame]) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut start = 0;
+
+        while start < frames.len() {
+            let run = frames[start..].iter().take_while(|frame| **frame == frames[start]).count();
+
+            for frame in &frames[start..start + run.min(REPEATED_FRAMES_SHOWN)] {
+                lines.push(frame.to_string());
+            }
+
+            if run > REPEATED_FRAMES_SHOWN {
+                lines.push(format!("[previous frame repeated {} more times]", run - REPEATED_FRAMES_SHOWN));
+            }
+
+            start += run;
+        }
+
+        lines
+    }
+}
+
+impl Interpreter {
+    /// Keep the frames an error unwinds through, unless an inner call
+    /// already did. Only the first call the error leaves still has them all.
+    pub fn record_traceback(&mut self, error: &RunTimeError) {
+        if self.traceback.is_some() {
+            return;
+        }
+
+        let mut line = match error {
+            RunTimeError::Error(error) => error.token.as_ref().map(|token| token.line),
+            RunTimeError::Throw(_, token) => Some(token.line),
+            RunTimeError::Aborted(_, token) => token.as_ref().map(|token| token.line),
+            _ => return,
+        };
+
+        let mut frames: Vec<TraceFrame> = Vec::with_capacity(self.calls.len() + 1);
+
+        for call in self.calls.iter().rev() {
+            frames.push(TraceFrame {
+                function: call.function.clone(),
+                line,
+            });
+            line = Some(call.line);
+        }
+
+        frames.push(TraceFrame::script(line));
+        self.traceback = Some(frames);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(function: &str, line: usize) -> TraceFrame {
+        TraceFrame {
+            function: String::from(function),
+            line: Some(line),
+        }
+    }
+
+    #[test]
+    fn render_traceback() {
+        let mut frames = vec![frame("Counter.addCount", 12)];
+        frames.extend(vec![frame("count", 4); 5]);
+        frames.push(TraceFrame::script(Some(20)));
+
+        assert_eq!(TraceFrame::render(&frames), vec![
+            "at Counter.addCount (line 12)",
+            "at count (line 4)",
+            "at count (line 4)",
+            "at count (line 4)",
+            "[previous frame repeated 2 more times]",
+            "at main script (line 20)",
+        ]);
+        assert_eq!(TraceFrame::script(None).to_string(), "at main script");
+    }
+}
@@ -1,3 +1,8 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::process;
+use runner::options::Options;
+
 mod scanner;
 mod grammer;
 mod vistor;
@@ -7,7 +12,17 @@ mod runner;
 mod interpreter;
 mod environment;
 mod resolver;
+mod diagnostic;
+mod vm;
 
 fn main() {
-    println!("Hello, world!");
+    let options = match Options::parse(env::args().skip(1), io::stdin().is_terminal()) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(runner::EXIT_USAGE);
+        },
+    };
+
+    process::exit(runner::execute(options));
 }
@@ -1,33 +1,79 @@
+use std::rc::Rc;
 use crate::scanner::token::{Token, TokenType};
 use crate::grammer::statement::Stmt;
 use parese_error::ParserError;
 
 mod parser_expr;
 mod parser_stmt;
-mod parese_error;
+pub mod parese_error;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<ParserError>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
         Parser {
-            tokens: tokens,
+            tokens: tokens.into_iter().filter(|token| token.typee != TokenType::DocComment).collect(),
             current: 0,
+            errors: Vec::new(),
         }
     }
 
-    pub fn parser(&mut self) -> Result<Vec<Stmt>, ParserError> {
+    pub fn parser(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
+        let (statements, errors) = self.parser_recover();
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parse the whole program, recovering from syntax errors at statement
+    /// boundaries. Returns the statements that could be parsed along with
+    /// every error found on the way.
+    pub fn parser_recover(&mut self) -> (Vec<Stmt>, Vec<ParserError>) {
         let mut statements: Vec<Stmt> = vec![];
 
         while !self.is_at_end() {
-            let statement = self.declaration()?;
-            statements.push(statement);   
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
 
-        Ok(statements)
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    /// Discard tokens until the start of the next statement after an error
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().typee == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().typee {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Throw
+                | TokenType::Try => return,
+                _ => {
+                    self.advance();
+                },
+            }
+        }
     }
 
     fn consume(&mut self, typee: TokenType, message: &str) -> Result<&Token, ParserError> {
@@ -1,4 +1,5 @@
 use std::fmt;
+use crate::diagnostic::{Diagnostic, Phase, Span};
 use super::*;
 
 #[derive(PartialEq)]
@@ -10,12 +11,11 @@ pub enum ParserError {
     },
 
     ExpectedExpression {
-        token_type: TokenType,
-        line: usize,
+        token: Token,
     },
 
     InvalidAssignmentTarget {
-        line: usize,
+        token: Token,
     },
 
     FunctionParamUpperLimit {
@@ -23,6 +23,22 @@ pub enum ParserError {
     }
 }
 
+impl ParserError {
+    /// Type of the token parsing failed at, if the error records it
+    pub fn found_type(&self) -> Option<TokenType> {
+        match self {
+            ParserError::TokenMisMatch { found, .. } => Some(found.typee),
+            ParserError::ExpectedExpression { token } => Some(token.typee),
+            _ => None,
+        }
+    }
+
+    /// Whether parsing failed only because the source ended too early
+    pub fn is_at_eof(&self) -> bool {
+        self.found_type() == Some(TokenType::Eof)
+    }
+}
+
 impl fmt::Debug for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
@@ -38,22 +54,21 @@ impl fmt::Debug for ParserError {
                 )
             },
             ParserError::ExpectedExpression { 
-                token_type,
-                line,
+                token,
             } => {
                 write!(
                     f,
                     "Expected expression, but found {:?} at line = {}",
-                    token_type, line,
+                    token.typee, token.line,
                 )
             },
             ParserError::InvalidAssignmentTarget {
-                line 
+                token 
             } => {
                 write!(
                     f,
                     "Invalid assignment target, found at line = {}",
-                    line,
+                    token.line,
                 )
             },
             ParserError::FunctionParamUpperLimit { 
@@ -68,3 +83,29 @@ impl fmt::Debug for ParserError {
         }
     }
 }
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Diagnostic {
+        match error {
+            ParserError::TokenMisMatch { found, message, .. } => {
+                Diagnostic::error(Phase::Parse, "E0101", message.clone())
+                    .with_span(Span::from(found))
+                    .with_label(format!("found {}", Diagnostic::describe(found)))
+            },
+            ParserError::ExpectedExpression { token } => {
+                Diagnostic::error(Phase::Parse, "E0102", String::from("Expected expression."))
+                    .with_span(Span::from(token))
+                    .with_label(format!("found {}", Diagnostic::describe(token)))
+            },
+            ParserError::InvalidAssignmentTarget { token } => {
+                Diagnostic::error(Phase::Parse, "E0103", String::from("Invalid assignment target."))
+                    .with_span(Span::from(token))
+                    .with_label(String::from("can't assign to the expression before this"))
+            },
+            ParserError::FunctionParamUpperLimit { token } => {
+                Diagnostic::error(Phase::Parse, "E0104", String::from("Can't have more than 255 arguments."))
+                    .with_span(Span::from(token))
+            },
+        }
+    }
+}
@@ -3,30 +3,49 @@ use crate::scanner::token;
 use super::*;
 
 impl Parser {
+    #[cfg(test)]
     pub fn parse_expr_debug(&mut self) -> Result<Expr, ParserError> {
         self.expression()
     }
 
+    /// Parse source made of exactly one expression, as typed into the prompt
+    pub fn expression_entry(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.expression()?;
+
+        if !self.is_at_end() {
+            return Err(ParserError::TokenMisMatch {
+                expected: TokenType::Eof,
+                found: self.peek().clone(),
+                message: String::from("Expect end of expression."),
+            });
+        }
+
+        Ok(expr)
+    }
+
     pub fn expression(&mut self) -> Result<Expr, ParserError> {
         self.assignment()
     }
 
     fn assignment(&mut self) -> Result<Expr, ParserError> {
-        let expr = self.equality()?;
+        let expr = self.or()?;
 
         if self.matches(TokenType::Equal) {
             let token_equal = self.previous().clone();
             let value: Expr = self.assignment()?;
 
             match expr {
-                Expr::Variable(token) => {
-                    return Ok(Expr::Assign(token.clone(), Box::new(value)))
+                Expr::Variable(token, _) => {
+                    return Ok(Expr::Assign(token, Box::new(value), Default::default()))
                 },
                 Expr::Get(object, propery) => {
                     return Ok(Expr::Set(object, propery, Box::new(value)));
                 },
+                Expr::Index(object, bracket, index) => {
+                    return Ok(Expr::SetIndex(object, bracket, index, Box::new(value)));
+                },
                 _ => {
-                    return Err(ParserError::InvalidAssignmentTarget { line: token_equal.line });
+                    return Err(ParserError::InvalidAssignmentTarget { token: token_equal });
                 }
             }            
         }
@@ -78,7 +97,7 @@ impl Parser {
     }
 
     fn comparision(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.term()?;
+        let mut expr = self.bitwise_or()?;
 
         while self.match_one_of(vec![
             TokenType::Greater,
@@ -87,7 +106,26 @@ impl Parser {
             TokenType::LessEqual,
         ]) {
             let operator = self.previous().clone();
-            let right = self.term()?;
+            let right = self.bitwise_or()?;
+            expr = Expr::Binary(
+                Box::new(expr),
+                operator,
+                Box::new(right),
+            );
+        }
+
+        Ok(expr)
+    }
+
+
+    fn bitwise_or(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.bitwise_xor()?;
+
+        while self.match_one_of(vec![
+            TokenType::Pipe,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.bitwise_xor()?;
             expr = Expr::Binary(
                 Box::new(expr),
                 operator,
@@ -98,6 +136,60 @@ impl Parser {
         Ok(expr)
     }
 
+    fn bitwise_xor(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.bitwise_and()?;
+
+        while self.match_one_of(vec![
+            TokenType::Caret,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.bitwise_and()?;
+            expr = Expr::Binary(
+                Box::new(expr),
+                operator,
+                Box::new(right),
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.shift()?;
+
+        while self.match_one_of(vec![
+            TokenType::Ampersand,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.shift()?;
+            expr = Expr::Binary(
+                Box::new(expr),
+                operator,
+                Box::new(right),
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.term()?;
+
+        while self.match_one_of(vec![
+            TokenType::LessLess,
+            TokenType::GreaterGreater,
+        ]) {
+            let operator = self.previous().clone();
+            let right = self.term()?;
+            expr = Expr::Binary(
+                Box::new(expr),
+                operator,
+                Box::new(right),
+            );
+        }
+
+        Ok(expr)
+    }
 
     fn term(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.factor()?;
@@ -123,7 +215,9 @@ impl Parser {
 
         while self.match_one_of(vec![
             TokenType::Slash,
-            TokenType::Star
+            TokenType::Star,
+            TokenType::Percent,
+            TokenType::TildeSlash,
         ]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
@@ -140,7 +234,8 @@ impl Parser {
     fn unary(&mut self) -> Result<Expr, ParserError> {
         if self.match_one_of(vec![
             TokenType::Bang,
-            TokenType::Minus
+            TokenType::Minus,
+            TokenType::Tilde,
         ]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
@@ -148,7 +243,22 @@ impl Parser {
             return Ok(Expr::Unary(operator, Box::new(right)));
         }
 
-        return self.class_init();
+        self.power()
+    }
+
+    // Binds tighter than unary operators on its left, `-2 ** 2` is -4, and
+    // groups to the right, the exponent may itself be negated
+    fn power(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.class_init()?;
+
+        if self.matches(TokenType::StarStar) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+
+            return Ok(Expr::Binary(Box::new(expr), operator, Box::new(right)));
+        }
+
+        Ok(expr)
     }
 
     fn class_init(&mut self) -> Result<Expr, ParserError> {
@@ -159,13 +269,13 @@ impl Parser {
             return Ok(Expr::New(init_token, Box::new(call_expr)));
         }
 
-        return self.call();
+        self.call()
     }
 
     fn call(&mut self) -> Result<Expr, ParserError> {
         let mut expr = self.primary()?;
 
-        while true {
+        loop {
             if self.matches(TokenType::LeftParen) {
                 expr = self.finsh_call(expr)?;
             } else if self.matches(TokenType::Dot) {
@@ -175,6 +285,12 @@ impl Parser {
                 )?.clone();
 
                 expr = Expr::Get(Box::new(expr), property);
+            } else if self.matches(TokenType::LeftBracket) {
+                let bracket = self.previous().clone();
+                let index = self.expression()?;
+                self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+
+                expr = Expr::Index(Box::new(expr), bracket, Box::new(index));
             } else {
                 break;
             }
@@ -222,8 +338,12 @@ impl Parser {
             }
         }
 
+        if self.matches(TokenType::Interpolation) {
+            return self.interpolation();
+        }
+
         if self.matches(TokenType::Identifier) {
-            return Ok(Expr::Variable(self.previous().clone()));
+            return Ok(Expr::Variable(self.previous().clone(), Default::default()));
         }
 
         if self.matches(TokenType::LeftParen) {
@@ -234,20 +354,37 @@ impl Parser {
             return Ok(Expr::Grouping(Box::new(expr)));
         }
 
+        // Already reported by the scanner, keep parsing as if it were a value
+        if self.matches(TokenType::Error) {
+            return Ok(Expr::Literal(ExprLiteral::Nil));
+        }
+
         if self.matches(TokenType::This) {
-            return Ok(Expr::This(self.previous().clone()));
+            return Ok(Expr::This(self.previous().clone(), Default::default()));
+        }
+
+        if self.matches(TokenType::Lambda) {
+            return self.lambda();
+        }
+
+        if self.matches(TokenType::LeftBracket) {
+            return self.list();
+        }
+
+        // Blocks are statements, so a brace here can only open a map
+        if self.matches(TokenType::LeftBrace) {
+            return self.map();
         }
 
         if self.matches(TokenType::Super) {
             let token = self.previous().clone();
             self.consume(TokenType::Dot, "Expect \'.\' afer super")?;
             let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?.clone();
-            return Ok(Expr::Super(token, Box::new(Expr::Variable(method))))
+            return Ok(Expr::Super(token, Box::new(Expr::Variable(method, Default::default())), Default::default()))
         }
 
         Err(ParserError::ExpectedExpression { 
-            token_type: self.peek().typee,
-            line: self.peek().line,
+            token: self.peek().clone(),
         })
     }
 }
@@ -256,6 +393,98 @@ impl Parser {
  * Helper function
  */
 impl Parser {
+    fn list(&mut self) -> Result<Expr, ParserError> {
+        let bracket = self.previous().clone();
+        let mut elements: Vec<Expr> = Vec::new();
+
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                elements.push(self.expression()?);
+
+                if !self.matches(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+
+        Ok(Expr::List(bracket, elements))
+    }
+
+    // Segments are literals between the interpolated expressions, the last
+    // one is a string token
+    fn interpolation(&mut self) -> Result<Expr, ParserError> {
+        let start = self.previous().clone();
+        let mut parts: Vec<Expr> = Vec::new();
+
+        loop {
+            if let Some(token::Literal::Str(segment)) = &self.previous().literal {
+                if !segment.is_empty() {
+                    parts.push(Expr::Literal(ExprLiteral::String(segment.clone())));
+                }
+            }
+
+            if self.previous().typee == TokenType::String {
+                break;
+            }
+
+            // Segments after the first start with the brace closing `${}`
+            let next = self.peek();
+            if matches!(next.typee, TokenType::String | TokenType::Interpolation) && next.lexeme.first() == Some(&b'}') {
+                return Err(ParserError::ExpectedExpression { token: next.clone() });
+            }
+
+            parts.push(self.expression()?);
+
+            if !self.matches(TokenType::Interpolation) {
+                self.consume(TokenType::String, "Expect '}' after interpolated expression.")?;
+            }
+        }
+
+        Ok(Expr::Interpolation(start, parts))
+    }
+
+    fn map(&mut self) -> Result<Expr, ParserError> {
+        let brace = self.previous().clone();
+        let mut pairs: Vec<(Expr, Expr)> = Vec::new();
+
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                let key = self.expression()?;
+                self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                let value = self.expression()?;
+                pairs.push((key, value));
+
+                if !self.matches(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+
+        Ok(Expr::Map(brace, pairs))
+    }
+
+    // Either `lambda (a) { ... }` or `lambda (a) => expression`
+    fn lambda(&mut self) -> Result<Expr, ParserError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect \'( \' after lambda")?;
+        let parameters = self.parameters()?;
+
+        let body = if self.matches(TokenType::Arrow) {
+            let arrow = self.previous().clone();
+            let value = self.expression()?;
+            Stmt::Block(vec![Stmt::Return(arrow, Some(value))])
+        } else {
+            self.consume(TokenType::LeftBrace, "Expect \' { \' or \'=>\' before lambda body")?;
+            self.block()?
+        };
+
+        Ok(Expr::Lambda(keyword, Rc::new(parameters), Rc::new(body)))
+    }
+
     fn finsh_call(&mut self, callee: Expr) -> Result<Expr, ParserError> {
         let mut arguments: Vec<Expr> = Vec::new();
 
@@ -308,6 +537,22 @@ mod tests {
         let source_expected: Vec<(String, String)> = vec![
             (String::from("(1+2) * 3 + 5"), String::from("(+ (* (group (+ 1 2)) 3) 5)")),
             (String::from("-1 * 2"), String::from("(* (- 1) 2)")),
+            (String::from("1 or 2 and 3"), String::from("(or 1 (and 2 3))")),
+            (String::from("1 and 2 or 3"), String::from("(or (and 1 2) 3)")),
+            (String::from("1 == 2 and 3"), String::from("(and (== 1 2) 3)")),
+            (String::from("1 or 2 or 3"), String::from("(or (or 1 2) 3)")),
+            (String::from("lambda (a, b) { return a; }"), String::from("(lambda a b)")),
+            (String::from("lambda () => 1"), String::from("(lambda )")),
+            (String::from("[1, 2 + 3]"), String::from("(list 1 (+ 2 3))")),
+            (String::from("a[0][1]"), String::from("(index (index idt Some(Identifier(\"a\")) 0) 1)")),
+            (String::from("a[0] = 1"), String::from("(set index idt Some(Identifier(\"a\")) 0 1)")),
+            (String::from("{\"a\": 1, 2: [3]}"), String::from("(map a 1 2 (list 3))")),
+            (String::from("\"a ${1 + 2} b ${\"c${3}\"}\""), String::from("(interpolation a  (+ 1 2)  b  (interpolation c 3))")),
+            (String::from("-2 ** 2"), String::from("(- (** 2 2))")),
+            (String::from("2 ** 3 ** -1"), String::from("(** 2 (** 3 (- 1)))")),
+            (String::from("7 % 3 ~/ 2 * 4"), String::from("(* (~/ (% 7 3) 2) 4)")),
+            (String::from("1 | 2 ^ 3 & 4 << 1 + 1"), String::from("(| 1 (^ 2 (& 3 (<< 4 (+ 1 1)))))")),
+            (String::from("~1 & 3 == 2 >> 1"), String::from("(== (& (~ 1) 3) (>> 2 1))")),
         ];
 
         for (source, expected) in source_expected {
@@ -329,6 +574,14 @@ mod tests {
                     message: String::from("Expect ')' after expression."),
                 }
             ),
+            (
+                String::from("lambda (a) a"),
+                ParserError::TokenMisMatch {
+                    expected: TokenType::LeftBrace,
+                    found: Token { typee: TokenType::Identifier, lexeme: "a".as_bytes().to_vec(), literal: Some(token::Literal::Identifier(String::from("a"))), line: 1, col: 12 },
+                    message: String::from("Expect ' { ' or '=>' before lambda body"),
+                }
+            ),
         ];
 
         for (source, expected) in source_expected {
@@ -3,8 +3,22 @@ use crate::grammer::expression::{Expr, ExprLiteral};
 use super::*;
 
 impl Parser {
-    pub fn declaration(&mut self) -> Result<Stmt, ParserError> {
-        // TODO: error synchronize
+    pub fn declaration(&mut self) -> Option<Stmt> {
+        match self.declaration_stmt() {
+            Ok(stmt) => Some(stmt),
+            Err(error) => {
+                // Error tokens have already been reported by the scanner
+                if error.found_type() != Some(TokenType::Error) {
+                    self.errors.push(error);
+                }
+
+                self.synchronize();
+                None
+            },
+        }
+    }
+
+    fn declaration_stmt(&mut self) -> Result<Stmt, ParserError> {
         if self.matches(TokenType::Var) {
             return self.var_declaration();
         }
@@ -39,8 +53,8 @@ impl Parser {
         let mut superclass: Option<Expr> = None;
 
         if self.matches(TokenType::Extend) {
-            self.consume(TokenType::Identifier, "Expect super class name");
-            superclass = Some(Expr::Variable(self.previous().clone()));
+            self.consume(TokenType::Identifier, "Expect super class name")?;
+            superclass = Some(Expr::Variable(self.previous().clone(), Default::default()));
         }
 
 
@@ -57,10 +71,21 @@ impl Parser {
         Ok(Stmt::Class(name, superclass, methods))
     }
 
-    fn function(&mut self, function_type: FunctionType) -> Result<Stmt, ParserError> {
+    fn function(&mut self, _function_type: FunctionType) -> Result<Stmt, ParserError> {
         let function_name = self.consume(TokenType::Identifier, "Expect function name")?.clone();
         self.consume(TokenType::LeftParen, "Expect \'( \' after function name")?;
+        let parameters = self.parameters()?;
 
+        // Parse body
+        self.consume(TokenType::LeftBrace, "Expect \' { \' before function body")?;
+        let body = self.block()?;
+
+        Ok(Stmt::Function(function_name, Rc::new(parameters), Rc::new(body)))
+    }
+
+    /// Parse the parameters following an opening parenthesis, up to and
+    /// including the closing one
+    pub fn parameters(&mut self) -> Result<Vec<Token>, ParserError> {
         let mut parameters: Vec<Token> = Vec::new();
 
         if !self.check(TokenType::RightParen) {
@@ -79,11 +104,7 @@ impl Parser {
 
         self.consume(TokenType::RightParen, "Expect \' ) \' after parameters.")?;
 
-        // Parse body
-        self.consume(TokenType::LeftBrace, "Expect \' { \' before function body")?;
-        let body = self.block()?;
-
-        Ok(Stmt::Function(function_name, parameters, Box::new(body)))
+        Ok(parameters)
     }
  
     fn statement(&mut self) -> Result<Stmt, ParserError> {
@@ -111,18 +132,42 @@ impl Parser {
             return self.return_statement();
         }
 
+        if self.matches(TokenType::Throw) {
+            let token = self.previous().clone();
+            let value = self.expression()?;
+            self.consume(TokenType::Semicolon, "Expect ';' after thrown value.")?;
+            return Ok(Stmt::Throw(token, value));
+        }
+
+        if self.matches(TokenType::Try) {
+            return self.try_statement();
+        }
+
+        if self.matches(TokenType::Break) {
+            let token = self.previous().clone();
+            self.consume(TokenType::Semicolon, "Expect ';' after 'break'.")?;
+            return Ok(Stmt::Break(token));
+        }
+
+        if self.matches(TokenType::Continue) {
+            let token = self.previous().clone();
+            self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.")?;
+            return Ok(Stmt::Continue(token));
+        }
+
         self.expression_statement()
     }
 
-    fn block(&mut self) -> Result<Stmt, ParserError> {
+    pub fn block(&mut self) -> Result<Stmt, ParserError> {
         let mut statements: Vec<Stmt> = vec![];
 
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            let declaration = self.declaration()?;
-            statements.push(declaration);
+            if let Some(declaration) = self.declaration() {
+                statements.push(declaration);
+            }
         }
 
-        self.consume(TokenType::RightBrace, "Expect '}' after block.");
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
 
         Ok(Stmt::Block(statements))
     }
@@ -140,9 +185,9 @@ impl Parser {
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ParserError> {
-        self.consume(TokenType::LeftParen, "Expect \' ( \' after if");
+        self.consume(TokenType::LeftParen, "Expect \' ( \' after if")?;
         let condition_expr = self.expression()?;
-        self.consume(TokenType::RightParen, "Expect \' ) \' after if condition");
+        self.consume(TokenType::RightParen, "Expect \' ) \' after if condition")?;
 
         let then_branch = self.statement()?;
         let mut else_branch: Option<Stmt> = None;
@@ -155,19 +200,19 @@ impl Parser {
     }
 
     fn while_statement(&mut self) -> Result<Stmt, ParserError> {
-        self.consume(TokenType::LeftParen, "Expect \' ( \' after while");
+        self.consume(TokenType::LeftParen, "Expect \' ( \' after while")?;
         let condition = self.expression()?;
-        self.consume(TokenType::RightParen, "Expect \' ) \' after condition");
+        self.consume(TokenType::RightParen, "Expect \' ) \' after condition")?;
         let body = self.statement()?;
 
-        Ok(Stmt::While(condition, Box::new(body)))
+        Ok(Stmt::While(condition, Box::new(body), None))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, ParserError> {
-        self.consume(TokenType::LeftParen, "Expect \' ( \' after for");
+        self.consume(TokenType::LeftParen, "Expect \' ( \' after for")?;
 
         // Parse initializer
-        let mut initializer: Option<Stmt>;
+        let initializer: Option<Stmt>;
 
         if self.matches(TokenType::Semicolon) {
             initializer = None;
@@ -183,7 +228,7 @@ impl Parser {
             condition = Some(self.expression()?);
         }
 
-        self.consume(TokenType::Semicolon, "Expect \' ; \' after loop condition");
+        self.consume(TokenType::Semicolon, "Expect \' ; \' after loop condition")?;
 
         // Parse increment
         let mut increment: Option<Expr> = None;
@@ -192,38 +237,50 @@ impl Parser {
             increment = Some(self.expression()?);
         }
 
-        self.consume(TokenType::RightParen, "Expect \' ) \' after for clauses.");
+        self.consume(TokenType::RightParen, "Expect \' ) \' after for clauses.")?;
 
         // Parse body
         let body = self.statement()?;
 
         // Convert for loop to while loop, as desugaring
-        let mut while_body: Stmt;
-        let while_condition: Expr;
-
         // Create condition for while statement
-        if condition.is_none() {
-            while_condition = Expr::Literal(ExprLiteral::True);
-        } else {
-            while_condition = condition.unwrap();
+        let while_condition = condition.unwrap_or(Expr::Literal(ExprLiteral::True));
+
+        // The increment is kept apart from the body so that `continue`
+        // still runs it
+        let mut while_body = Stmt::While(while_condition, Box::new(body), increment);
+
+        // { initializer; while (condition) body }
+        if let Some(initializer) = initializer {
+            while_body = Stmt::Block(vec![initializer, while_body]);
         }
 
-        // { body; increment }
-        if !increment.is_none() {
-            while_body = Stmt::Block(vec![body, Stmt::Expr(increment.unwrap())]);
-        } else {
-            while_body = body;
+        Ok(while_body)
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParserError> {
+        self.consume(TokenType::LeftBrace, "Expect ' { ' after try")?;
+        let body = self.block()?;
+
+        let mut catch: Option<(Token, Box<Stmt>)> = None;
+
+        if self.matches(TokenType::Catch) {
+            self.consume(TokenType::LeftParen, "Expect ' ( ' after catch")?;
+            let name = self.consume(TokenType::Identifier, "Expect exception variable name")?.clone();
+            self.consume(TokenType::RightParen, "Expect ' ) ' after exception variable")?;
+            self.consume(TokenType::LeftBrace, "Expect ' { ' before catch body")?;
+            catch = Some((name, Box::new(self.block()?)));
         }
 
-        // while (condition) { body; increment }
-        while_body = Stmt::While(while_condition, Box::new(while_body));
+        let mut finally: Option<Box<Stmt>> = None;
 
-        // { initializer; while (condition) { body, increment } }
-        if !initializer.is_none() {
-            while_body = Stmt::Block(vec![initializer.unwrap(), while_body]);
+        if catch.is_none() || self.check(TokenType::Finally) {
+            self.consume(TokenType::Finally, "Expect 'catch' or 'finally' after try block")?;
+            self.consume(TokenType::LeftBrace, "Expect ' { ' after finally")?;
+            finally = Some(Box::new(self.block()?));
         }
 
-        Ok(while_body)
+        Ok(Stmt::Try(Box::new(body), catch, finally))
     }
 
     fn return_statement(&mut self) -> Result<Stmt, ParserError> {
@@ -234,8 +291,69 @@ impl Parser {
             value = Some(self.expression()?);
         }
 
-        self.consume(TokenType::Semicolon, "Expect \';\' after return value");
+        self.consume(TokenType::Semicolon, "Expect \';\' after return value")?;
 
         Ok(Stmt::Return(token, value))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> (Vec<Stmt>, Vec<ParserError>) {
+        let tokens: Vec<Token> = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        Parser::new(tokens).parser_recover()
+    }
+
+    #[test]
+    fn recover_multiple_errors() {
+        let (statements, errors) = parse("
+            var a = ;
+            print a;
+            var = 2;
+            print (1;
+            print 3;
+        ");
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(&errors[0], ParserError::ExpectedExpression { token } if token.line == 2));
+        assert!(matches!(&errors[1], ParserError::TokenMisMatch { found, .. } if found.line == 4));
+        assert!(matches!(&errors[2], ParserError::TokenMisMatch { found, .. } if found.line == 5));
+    }
+
+    #[test]
+    fn recover_inside_block() {
+        let (statements, errors) = parse("
+            {
+                var a = 1 +;
+                print a;
+            }
+            print 2;
+        ");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(&statements[0], Stmt::Block(inner) if inner.len() == 1));
+    }
+
+    #[test]
+    fn recover_error_at_eof() {
+        let (statements, errors) = parse("print 1; print (2");
+
+        assert_eq!(statements.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_at_eof());
+    }
+
+    #[test]
+    fn recover_skip_error_tokens() {
+        let tokens = Scanner::new(String::from("var a = $; var # = 1; print a;")).scan_tokens_recover().0;
+        let (statements, errors) = Parser::new(tokens).parser_recover();
+
+        assert_eq!(statements.len(), 2);
+        assert!(errors.is_empty());
+    }
+}
@@ -8,10 +8,30 @@ use resolve_error::ResolveError;
 pub mod resolve_error;
 
 pub struct Resolver<'a> {
-    pub scopes: Vec<HashMap<String, bool>>,
+    pub scopes: Vec<HashMap<String, Binding>>,
     pub interpreter: &'a mut Interpreter,
     pub current_function: FunctionStatus,
     pub current_class: ClassStatus,
+    pub current_loop: LoopStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub defined: bool,
+    // Index of the variable in its runtime environment
+    pub slot: usize,
+    // Where the name was declared, `this` and `super` have no declaration
+    pub token: Option<Token>,
+}
+
+impl Binding {
+    pub fn implicit(slot: usize) -> Binding {
+        Binding {
+            defined: true,
+            slot,
+            token: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -34,16 +54,22 @@ pub enum ClassStatus {
     Class,
 }
 
-impl<'a> Resolver<'a> {
-    pub fn new(interpreter: &'a mut Interpreter) -> Resolver {
-        let mut init_scopes: Vec<HashMap<String, bool>> = Vec::new();
-        init_scopes.push(HashMap::new());
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopStatus {
+    None,
+    Loop,
+}
 
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Resolver<'a> {
+        // Top level declarations are globals, which are looked up by name at
+        // runtime so that they survive across several resolver runs
         Resolver {
             interpreter,
-            scopes: init_scopes,
+            scopes: Vec::new(),
             current_function: FunctionStatus::None,
             current_class: ClassStatus::None,
+            current_loop: LoopStatus::None,
         }
     }
 
@@ -63,10 +89,6 @@ impl<'a> Resolver<'a> {
         self.scopes.is_empty()
     }
 
-    pub fn scope_size(&self) -> usize {
-        self.scopes.len()
-    }
-
     pub fn set_current_val(&mut self, token: &Token, val: bool, operation: SetValueType) -> Result<(), ResolveError> {
         if self.is_scope_empty() {
             return Ok(());
@@ -77,19 +99,25 @@ impl<'a> Resolver<'a> {
 
         match operation {
             SetValueType::Declar => {
-                if current_scope.contains_key(&name) {
+                if let Some(previous) = current_scope.get(&name) {
                     // Duplicated declar
-                    return Err(ResolveError::CommonError { 
+                    return Err(ResolveError::AlreadyDeclared { 
                         token: token.clone(),
-                        message: String::from("Already a variable with this name in this scope."),
+                        previous: previous.token.clone().map(Box::new),
                     })
                 }
 
-                current_scope.insert(name, val);
+                // Locals get slots in the order they are declared
+                let slot = current_scope.len();
+                current_scope.insert(name, Binding { defined: val, slot, token: Some(token.clone()) });
                 Ok(())
             },
             SetValueType::Define => {
-                current_scope.insert(name, val);
+                let slot = current_scope.len();
+                current_scope
+                    .entry(name)
+                    .or_insert_with(|| Binding { defined: val, slot, token: Some(token.clone()) })
+                    .defined = val;
                 Ok(())
             }
         }
@@ -101,7 +129,7 @@ impl<'a> Resolver<'a> {
         }
 
         let current_scope = self.scopes.last().unwrap();
-        current_scope.get(&name).copied()
+        current_scope.get(&name).map(|binding| binding.defined)
     }
 
 
@@ -1,13 +1,20 @@
 use std::fmt;
 use crate::{scanner::token::Token};
+use crate::diagnostic::{Diagnostic, Phase, Span};
 
 pub enum ResolveError {
     CommonError {
         token: Token,
         message: String,
-    }
+    },
+    AlreadyDeclared {
+        token: Token,
+        previous: Option<Box<Token>>,
+    },
 }
 
+const ALREADY_DECLARED: &str = "Already a variable with this name in this scope.";
+
 impl fmt::Debug for ResolveError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
@@ -17,7 +24,35 @@ impl fmt::Debug for ResolveError {
                     "Resolve Error, token {:?} found at line {:?}: {:?}",
                     token.literal, token.line, message,
                 )
-            }
+            },
+            ResolveError::AlreadyDeclared { token, .. } => {
+                write!(
+                    f,
+                    "Resolve Error, token {:?} found at line {:?}: {:?}",
+                    token.literal, token.line, ALREADY_DECLARED,
+                )
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<&ResolveError> for Diagnostic {
+    fn from(error: &ResolveError) -> Diagnostic {
+        match error {
+            ResolveError::CommonError { token, message } => {
+                Diagnostic::error(Phase::Resolve, "E0201", message.clone())
+                    .with_span(Span::from(token))
+            },
+            ResolveError::AlreadyDeclared { token, previous } => {
+                let diagnostic = Diagnostic::error(Phase::Resolve, "E0202", String::from(ALREADY_DECLARED))
+                    .with_span(Span::from(token))
+                    .with_note(String::from("use assignment to change the value"));
+
+                match previous {
+                    Some(previous) => diagnostic.with_secondary(Span::from(previous.as_ref()), String::from("first declared here")),
+                    None => diagnostic,
+                }
+            },
+        }
+    }
+}
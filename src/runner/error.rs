@@ -1,16 +1,100 @@
 use crate::{
+    diagnostic::{Diagnostic, Phase, Span},
+    interpreter::budget::Abort,
     scanner::token::Token,
-    value::LValue
+    value::{exception::Exception, LValue},
 };
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RunTimeError {
     Error(CommonError),
     Return(LValue),
+    Break(Token),
+    Continue(Token),
+    // Value of a `throw` statement
+    Throw(LValue, Token),
+    // Run stopped by its budget, past every catch and finally clause
+    Aborted(Abort, Option<Token>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CommonError {
     pub message: String,
     pub token: Option<Token>,
-}
\ No newline at end of file
+}
+impl RunTimeError {
+    /// Point errors raised without a token, such as those of native
+    /// functions, at `token`
+    pub fn or_token(self, token: &Token) -> RunTimeError {
+        match self {
+            RunTimeError::Error(CommonError { message, token: None }) => RunTimeError::Error(CommonError {
+                message,
+                token: Some(token.clone()),
+            }),
+            error => error,
+        }
+    }
+
+    /// Error raised by `throw value`, throwing a caught runtime error
+    /// raises it again as it was
+    pub fn thrown(value: LValue, token: &Token) -> RunTimeError {
+        match value {
+            LValue::Exception(exception) => RunTimeError::Error(CommonError {
+                message: exception.message.clone(),
+                token: exception.token.clone(),
+            }),
+            value => RunTimeError::Throw(value, token.clone()),
+        }
+    }
+
+    /// Value a `catch` clause receives for the error, or the error itself
+    /// when it only unwinds to a function or a loop
+    pub fn into_caught(self) -> Result<LValue, RunTimeError> {
+        match self {
+            RunTimeError::Error(CommonError { message, token }) => {
+                Ok(LValue::Exception(Rc::new(Exception { message, token })))
+            },
+            RunTimeError::Throw(value, _) => Ok(value),
+            error => Err(error),
+        }
+    }
+}
+
+impl From<&RunTimeError> for Diagnostic {
+    fn from(error: &RunTimeError) -> Diagnostic {
+        match error {
+            RunTimeError::Error(error) => {
+                let diagnostic = Diagnostic::error(Phase::Runtime, "E0301", error.message.clone());
+
+                match &error.token {
+                    Some(token) => diagnostic.with_span(Span::from(token)),
+                    None => diagnostic,
+                }
+            },
+            RunTimeError::Return(_) => {
+                Diagnostic::error(Phase::Runtime, "E0302", String::from("Can't return from top-level code."))
+            },
+            RunTimeError::Break(token) => {
+                Diagnostic::error(Phase::Runtime, "E0302", String::from("Can't use 'break' outside of a loop."))
+                    .with_span(Span::from(token))
+            },
+            RunTimeError::Throw(value, token) => {
+                Diagnostic::error(Phase::Runtime, "E0301", format!("Uncaught exception: {}", value.repr()))
+                    .with_span(Span::from(token))
+            },
+            RunTimeError::Continue(token) => {
+                Diagnostic::error(Phase::Runtime, "E0302", String::from("Can't use 'continue' outside of a loop."))
+                    .with_span(Span::from(token))
+            },
+            RunTimeError::Aborted(abort, token) => {
+                let diagnostic = Diagnostic::error(Phase::Runtime, "E0303", abort.to_string());
+
+                match token {
+                    Some(token) => diagnostic.with_span(Span::from(token)),
+                    None => diagnostic,
+                }
+            },
+        }
+    }
+}
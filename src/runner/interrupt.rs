@@ -0,0 +1,56 @@
+use std::sync::OnceLock;
+use crate::interpreter::budget::CancelHandle;
+
+// Runs that Ctrl-C cancels, set once for the process
+static ON_INTERRUPT: OnceLock<CancelHandle> = OnceLock::new();
+
+/// Make Ctrl-C cancel the runs `handle` belongs to instead of ending the
+/// process. Only the first handle given is kept. Returns whether Ctrl-C
+/// can be caught on this platform.
+pub fn cancel_on_interrupt(handle: CancelHandle) -> bool {
+    if ON_INTERRUPT.set(handle).is_err() {
+        return true;
+    }
+
+    install()
+}
+
+// Only touches atomics, which is all a signal handler may do
+extern "C" fn on_interrupt(_signal: i32) {
+    if let Some(handle) = ON_INTERRUPT.get() {
+        handle.cancel();
+    }
+}
+
+#[cfg(unix)]
+fn install() -> bool {
+    let handler = on_interrupt as extern "C" fn(i32) as libc::sighandler_t;
+
+    // Safe as the handler is async-signal-safe
+    unsafe { libc::signal(libc::SIGINT, handler) != libc::SIG_ERR }
+}
+
+#[cfg(not(unix))]
+fn install() -> bool {
+    let _ = on_interrupt;
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::interpreter::budget::{Abort, Budget};
+    use crate::runner::error::RunTimeError;
+
+    #[test]
+    fn interrupt_cancels_runs() {
+        // Calls the handler itself, a real SIGINT would reach the whole
+        // test binary
+        let mut budget = Budget::new();
+        ON_INTERRUPT.set(budget.cancel_handle()).unwrap();
+        assert_eq!(budget.check(None), Ok(()));
+
+        on_interrupt(libc::SIGINT);
+        assert_eq!(budget.check(None), Err(RunTimeError::Aborted(Abort::Cancelled, None)));
+    }
+}
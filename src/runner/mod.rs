@@ -1 +1,425 @@
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod interrupt;
+pub mod options;
+pub mod repl;
+
+use std::fs;
+use std::io::{self, Read};
+use std::mem;
+use std::thread;
+use std::time::Duration;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::Interpreter;
+use crate::interpreter::trace::TraceFrame;
+use crate::parser::Parser;
+use crate::parser::parese_error::ParserError;
+use crate::resolver::Resolver;
+use crate::resolver::resolve_error::ResolveError;
+use crate::scanner::{Scanner, ScannerError};
+use crate::scanner::token::Token;
+use crate::value::LValue;
+use crate::vm::Vm;
+use crate::vm::compiler::Compiler;
+use crate::vm::compile_error::CompileError;
+use error::RunTimeError;
+use options::{Engine, ErrorFormat, Mode, Options, USAGE};
+use repl::Repl;
+
+// Exit codes follow the BSD sysexits convention, as clox does
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_USAGE: i32 = 64;
+pub const EXIT_DATA_ERROR: i32 = 65;
+pub const EXIT_SOFTWARE: i32 = 70;
+pub const EXIT_IO_ERROR: i32 = 74;
+
+#[derive(Debug)]
+pub enum RunError {
+    // Lexical and grammar errors are reported together
+    Syntax(Vec<ScannerError>, Vec<ParserError>),
+    Resolve(ResolveError),
+    Compile(CompileError),
+    // Along with the frames it unwound through, innermost first
+    Runtime(Box<RunTimeError>, Vec<TraceFrame>),
+}
+
+impl RunError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Syntax(_, _) | RunError::Resolve(_) | RunError::Compile(_) => EXIT_DATA_ERROR,
+            RunError::Runtime(_, _) => EXIT_SOFTWARE,
+        }
+    }
+
+    /// Every problem this error stands for, in source order for syntax errors
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            RunError::Syntax(scan_errors, parse_errors) => scan_errors
+                .iter()
+                .map(Diagnostic::from)
+                .chain(parse_errors.iter().map(Diagnostic::from))
+                .collect(),
+            RunError::Resolve(error) => vec![Diagnostic::from(error)],
+            RunError::Compile(error) => vec![Diagnostic::from(error)],
+            RunError::Runtime(error, traceback) => {
+                let mut diagnostic = Diagnostic::from(error.as_ref());
+
+                // A lone frame of the script adds nothing to the span
+                if traceback.len() > 1 {
+                    for line in TraceFrame::render(traceback) {
+                        diagnostic = diagnostic.with_note(line);
+                    }
+                }
+
+                vec![diagnostic]
+            },
+        }
+    }
+
+    /// Render the diagnostics against the source they were found in
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let rendered: Vec<String> = self
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.render(file_name, source))
+            .collect();
+
+        rendered.join("\n")
+    }
+
+    /// One JSON object per line for every diagnostic
+    pub fn render_json(&self, file_name: &str) -> String {
+        self.diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.to_json(file_name) + "\n")
+            .collect()
+    }
+
+    /// Render in the format asked for on the command line
+    pub fn render_as(&self, format: ErrorFormat, file_name: &str, source: &str) -> String {
+        match format {
+            ErrorFormat::Human => self.render(file_name, source),
+            ErrorFormat::Json => self.render_json(file_name),
+        }
+    }
+}
+
+pub struct Runner {
+    pub interpreter: Interpreter,
+    pub error_format: ErrorFormat,
+    pub engine: Engine,
+}
+
+impl Runner {
+    pub fn new() -> Runner {
+        Runner {
+            interpreter: Interpreter::new(),
+            error_format: ErrorFormat::Human,
+            engine: Engine::Tree,
+        }
+    }
+
+    /// Runner set up as the command line asks
+    pub fn with_options(options: &Options) -> Runner {
+        let mut runner = Runner::new();
+        runner.error_format = options.error_format;
+        runner.engine = options.engine;
+        runner.interpreter.max_call_depth = options.max_call_depth;
+        runner.interpreter.budget.max_steps = options.max_steps;
+        runner.interpreter.budget.timeout = options.timeout.map(Duration::from_millis);
+        runner
+    }
+
+    /// Forget every definition, keeping the limits and output of the
+    /// interpreter
+    pub fn reset(&mut self) {
+        let mut interpreter = Interpreter::new();
+        interpreter.max_call_depth = self.interpreter.max_call_depth;
+        mem::swap(&mut interpreter.budget, &mut self.interpreter.budget);
+        mem::swap(&mut interpreter.output, &mut self.interpreter.output);
+        self.interpreter = interpreter;
+    }
+
+    /// Scan, parse, resolve and interpret one piece of source code
+    pub fn run(&mut self, source: String) -> Result<(), RunError> {
+        let (tokens, scan_errors) = Scanner::new(source).scan_tokens_recover();
+        self.run_tokens(tokens, scan_errors)
+    }
+
+    /// Run scanned tokens. Scan errors are reported along with the parse
+    /// errors of the rest of the source.
+    pub fn run_tokens(&mut self, tokens: Vec<Token>, scan_errors: Vec<ScannerError>) -> Result<(), RunError> {
+        let (statements, parse_errors) = Parser::new(tokens).parser_recover();
+
+        if !scan_errors.is_empty() || !parse_errors.is_empty() {
+            return Err(RunError::Syntax(scan_errors, parse_errors));
+        }
+
+        Resolver::new(&mut self.interpreter)
+            .resolve(&statements)
+            .map_err(RunError::Resolve)?;
+
+        match self.engine {
+            Engine::Tree => {
+                let result = self.interpreter.interpret(statements);
+                result.map_err(|error| self.runtime_error(error))
+            },
+            Engine::Vm => {
+                let script = Compiler::new(&mut self.interpreter.globals)
+                    .compile(&statements)
+                    .map_err(RunError::Compile)?;
+
+                let result = Vm::new(&mut self.interpreter).run(script);
+                result.map(|_| ()).map_err(|error| self.runtime_error(error))
+            },
+        }
+    }
+
+    /// Evaluate tokens that form a single expression and return its value
+    pub fn evaluate_tokens(&mut self, tokens: Vec<Token>) -> Result<LValue, RunError> {
+        let expr = Parser::new(tokens)
+            .expression_entry()
+            .map_err(|error| RunError::Syntax(Vec::new(), vec![error]))?;
+
+        Resolver::new(&mut self.interpreter)
+            .resolve_expr(&expr)
+            .map_err(RunError::Resolve)?;
+
+        let result = match self.engine {
+            Engine::Tree => {
+                self.interpreter.budget.start();
+                self.interpreter.evaluate(&expr)
+            },
+            Engine::Vm => {
+                let script = Compiler::new(&mut self.interpreter.globals)
+                    .compile_expr(&expr)
+                    .map_err(RunError::Compile)?;
+
+                Vm::new(&mut self.interpreter).run(script)
+            },
+        };
+
+        result.map_err(|error| self.runtime_error(error))
+    }
+
+    fn runtime_error(&mut self, error: RunTimeError) -> RunError {
+        let traceback = self.interpreter.traceback.take().unwrap_or_default();
+        RunError::Runtime(Box::new(error), traceback)
+    }
+
+    /// Run a whole program and turn the outcome into a process exit code,
+    /// `file_name` is only used to report errors
+    pub fn run_program(&mut self, file_name: &str, source: String) -> i32 {
+        match self.run(source.clone()) {
+            Ok(_) => EXIT_OK,
+            Err(error) => {
+                eprint!("{}", error.render_as(self.error_format, file_name, &source));
+                error.exit_code()
+            },
+        }
+    }
+
+}
+
+/// Entry point used by `main` once the command line has been parsed
+pub fn execute(options: Options) -> i32 {
+    // Every call of the tree-walking interpreter recurses on the native
+    // stack, the main thread's is too small for the default call depth
+    let stack_size = match options.stack_size {
+        Some(stack_size) => stack_size,
+        None => Interpreter::stack_size(options.max_call_depth),
+    };
+
+    let thread = thread::Builder::new()
+        .name(String::from("interpreter"))
+        .stack_size(stack_size)
+        .spawn(move || execute_here(options));
+
+    match thread.map(|thread| thread.join()) {
+        Ok(Ok(code)) => code,
+        Ok(Err(_)) => EXIT_SOFTWARE,
+        Err(error) => {
+            eprintln!("Could not start the interpreter thread: {}", error);
+            EXIT_SOFTWARE
+        },
+    }
+}
+
+// Run on the interpreter thread
+fn execute_here(options: Options) -> i32 {
+    let mut runner = Runner::with_options(&options);
+
+    match options.mode {
+        Mode::Help => {
+            println!("{}", USAGE);
+            EXIT_OK
+        },
+        Mode::Repl => Repl::new(runner, Repl::default_history_path()).run(),
+        Mode::Eval(source) => runner.run_program("<eval>", source),
+        Mode::File(path) => match fs::read_to_string(&path) {
+            Ok(source) => runner.run_program(&path, source),
+            Err(error) => {
+                eprintln!("Could not read file '{}': {}", path, error);
+                EXIT_IO_ERROR
+            },
+        },
+        Mode::Stdin => {
+            let mut source = String::new();
+            match io::stdin().read_to_string(&mut source) {
+                Ok(_) => runner.run_program("<stdin>", source),
+                Err(error) => {
+                    eprintln!("Could not read stdin: {}", error);
+                    EXIT_IO_ERROR
+                },
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::DEFAULT_MAX_CALL_DEPTH;
+
+    #[test]
+    fn run_exit_codes() {
+        let source_expected: Vec<(String, i32)> = vec![
+            (String::from("var a = 1; print a;"), EXIT_OK),
+            (String::from("var a = $;"), EXIT_DATA_ERROR),
+            (String::from("var a = ;"), EXIT_DATA_ERROR),
+            (String::from("{ var a = 1; var a = 2; }"), EXIT_DATA_ERROR),
+            (String::from("print 1 / true;"), EXIT_SOFTWARE),
+            (String::from("break;"), EXIT_DATA_ERROR),
+            (String::from("throw 1;"), EXIT_SOFTWARE),
+            (String::from("try { throw 1; } catch (e) {}"), EXIT_OK),
+            (String::from("try { print 1; }"), EXIT_DATA_ERROR),
+            (String::from("while (true) { fun f() { continue; } }"), EXIT_DATA_ERROR),
+        ];
+
+        for (source, expected) in source_expected {
+            assert_eq!(Runner::new().run_program("<test>", source), expected);
+        }
+    }
+
+    #[test]
+    fn render_errors() {
+        let source = "var a = 1;\nprint a +;\nvar b = $;\n";
+        let error = Runner::new().run(String::from(source)).unwrap_err();
+
+        assert_eq!(
+            error.render("test.lox", source),
+            "error[E0001]: Unexpected character $\n \
+             --> test.lox:3:9\n  \
+             |\n\
+             3 | var b = $;\n  \
+             |         ^\n\
+             \n\
+             error[E0102]: Expected expression.\n \
+             --> test.lox:2:10\n  \
+             |\n\
+             2 | print a +;\n  \
+             |          ^ found `;`\n",
+        );
+
+        let source = "var a = 12px;\n";
+        let error = Runner::new().run(String::from(source)).unwrap_err();
+
+        assert_eq!(
+            error.render("test.lox", source),
+            "error[E0001]: Invalid number literal '12px'\n \
+             --> test.lox:1:9\n  \
+             |\n\
+             1 | var a = 12px;\n  \
+             |         ^^^^\n",
+        );
+
+        let source = "{\n  var count = 1;\n  var count = 2;\n}\n";
+        let error = Runner::new().run(String::from(source)).unwrap_err();
+
+        assert_eq!(
+            error.render("test.lox", source),
+            "error[E0202]: Already a variable with this name in this scope.\n \
+             --> test.lox:3:7\n  \
+             |\n\
+             2 |   var count = 1;\n  \
+             |       ----- first declared here\n\
+             3 |   var count = 2;\n  \
+             |       ^^^^^\n  \
+             |\n  \
+             = note: use assignment to change the value\n",
+        );
+
+        let source = "var a = 1;\nprint a / \"b\";\n";
+        let error = Runner::new().run(String::from(source)).unwrap_err();
+
+        assert_eq!(error.diagnostics()[0].code, "E0301");
+        assert_eq!(
+            error.render_json("test.lox"),
+            "{\"file\":\"test.lox\",\"start_line\":2,\"start_col\":9,\"end_line\":2,\"end_col\":10,\
+             \"severity\":\"error\",\"phase\":\"runtime\",\"code\":\"E0301\",\"message\":\"Operation divide only supports for Number\"}\n",
+        );
+        assert_eq!(error.diagnostics()[0].span.map(|span| span.line), Some(2));
+
+        let source = "var a = 12px;\n/* open\n  comment";
+        let error = Runner::new().run(String::from(source)).unwrap_err();
+
+        assert_eq!(
+            error.render_json("test.lox"),
+            "{\"file\":\"test.lox\",\"start_line\":1,\"start_col\":9,\"end_line\":1,\"end_col\":13,\
+             \"severity\":\"error\",\"phase\":\"scan\",\"code\":\"E0001\",\"message\":\"Invalid number literal '12px'\"}\n\
+             {\"file\":\"test.lox\",\"start_line\":2,\"start_col\":1,\"end_line\":3,\"end_col\":10,\
+             \"severity\":\"error\",\"phase\":\"scan\",\"code\":\"E0003\",\"message\":\"Unterminated block comment\"}\n",
+        );
+    }
+
+    #[test]
+    fn default_call_depth() {
+        let sources = [
+            "fun r(n) { return r(n + 1); }\nr(0);",
+            // Statements and expressions around the call take more stack
+            "fun r(n) {\n  { while (true) { try { for (var i = 0; i < 1; i = i + 1) {\n    if (true) return [r(n + 1)][0] + 1 * (2 + (3 + n));\n  } } catch (e) { throw e; } } }\n}\nr(0);",
+        ];
+
+        for engine in [Engine::Tree, Engine::Vm] {
+            for source in sources {
+                let thread = thread::Builder::new()
+                    .stack_size(Interpreter::stack_size(DEFAULT_MAX_CALL_DEPTH))
+                    .spawn(move || {
+                        let mut runner = Runner::new();
+                        runner.engine = engine;
+
+                        match runner.run(String::from(source)) {
+                            Err(RunError::Runtime(error, _)) => match *error {
+                                RunTimeError::Error(error) => error.message,
+                                error => panic!("Unexpected error {:?}", error),
+                            },
+                            result => panic!("Unexpected result {:?}", result),
+                        }
+                    })
+                    .unwrap();
+
+                assert_eq!(thread.join().unwrap(), "Stack overflow in r: more than 1000 nested calls.");
+            }
+        }
+    }
+
+    #[test]
+    fn render_traceback() {
+        let source = "class Counter {\n  add() { return this.count + 1; }\n}\nfun run(counter) { return counter.add(); }\nrun(Counter());\n";
+
+        for engine in [Engine::Tree, Engine::Vm] {
+            let mut runner = Runner::new();
+            runner.engine = engine;
+            let error = runner.run(String::from(source)).unwrap_err();
+
+            assert_eq!(error.diagnostics()[0].notes, vec![
+                "at Counter.add (line 2)",
+                "at run (line 4)",
+                "at main script (line 5)",
+            ]);
+            assert!(runner.interpreter.calls.is_empty());
+        }
+
+        let error = Runner::new().run(String::from("var a;\nprint a.b;")).unwrap_err();
+        assert!(error.diagnostics()[0].notes.is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use crate::interpreter::DEFAULT_MAX_CALL_DEPTH;
+
+pub const USAGE: &str = "\
+Usage: craft_interpreter [options] [script.lox | -]
+
+Options:
+    -e, --eval <code>          Evaluate the given source code
+    --error-format <format>    Report errors as `human` (default) or `json`,
+                               one object per line
+    --engine <engine>          Run with the tree-walking interpreter `tree`
+                               (default) or the bytecode virtual machine `vm`
+    --max-call-depth <calls>   Nested calls allowed before a stack overflow
+                               error is raised, 1000 by default
+    --stack-size <MiB>         Native stack of the interpreter, by default
+                               large enough for the max call depth
+    --max-steps <steps>        Stop programs once they took this many steps
+    --timeout <ms>             Stop programs running for longer than this
+    -h, --help                 Print this help message
+
+Without a script, source is read from stdin when it is not a terminal,
+otherwise an interactive prompt is started. Use `-` to force reading stdin.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    File(String),
+    Eval(String),
+    Stdin,
+    Repl,
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+/// Backend executing programs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Engine {
+    Tree,
+    Vm,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub mode: Mode,
+    pub error_format: ErrorFormat,
+    pub engine: Engine,
+    pub max_call_depth: usize,
+    // In bytes, given in MiB, sized from the max call depth when not given
+    pub stack_size: Option<usize>,
+    pub max_steps: Option<u64>,
+    // In milliseconds
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageError {
+    pub message: String,
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.message, USAGE)
+    }
+}
+
+impl Options {
+    /// Parse command line arguments, excluding the program name.
+    /// `stdin_is_terminal` decides what running without a script means.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I, stdin_is_terminal: bool) -> Result<Options, UsageError> {
+        let mut mode: Option<Mode> = None;
+        let mut error_format = ErrorFormat::Human;
+        let mut engine = Engine::Tree;
+        let mut max_call_depth = DEFAULT_MAX_CALL_DEPTH;
+        let mut stack_size: Option<usize> = None;
+        let mut max_steps: Option<u64> = None;
+        let mut timeout: Option<u64> = None;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if let Some(format) = Options::value_of("--error-format", &arg, &mut args)? {
+                error_format = match format.as_str() {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    _ => return Err(UsageError {
+                        message: format!("Unknown error format '{}'", format),
+                    }),
+                };
+                continue;
+            }
+
+            if let Some(name) = Options::value_of("--engine", &arg, &mut args)? {
+                engine = match name.as_str() {
+                    "tree" => Engine::Tree,
+                    "vm" => Engine::Vm,
+                    _ => return Err(UsageError {
+                        message: format!("Unknown engine '{}'", name),
+                    }),
+                };
+                continue;
+            }
+
+            if let Some(depth) = Options::value_of("--max-call-depth", &arg, &mut args)? {
+                max_call_depth = Options::positive("max call depth", &depth)?;
+                continue;
+            }
+
+            if let Some(size) = Options::value_of("--stack-size", &arg, &mut args)? {
+                let bytes = Options::positive("stack size", &size)?.checked_mul(1024 * 1024);
+                stack_size = Some(bytes.ok_or_else(|| UsageError {
+                    message: format!("Stack size '{}' is too large", size),
+                })?);
+                continue;
+            }
+
+            if let Some(steps) = Options::value_of("--max-steps", &arg, &mut args)? {
+                max_steps = Some(Options::positive("max steps", &steps)? as u64);
+                continue;
+            }
+
+            if let Some(milliseconds) = Options::value_of("--timeout", &arg, &mut args)? {
+                timeout = Some(Options::positive("timeout", &milliseconds)? as u64);
+                continue;
+            }
+
+            let next_mode = match arg.as_str() {
+                "-h" | "--help" => Mode::Help,
+                "-e" | "--eval" => {
+                    match args.next() {
+                        Some(code) => Mode::Eval(code),
+                        None => return Err(UsageError {
+                            message: format!("Missing code after '{}'", arg),
+                        }),
+                    }
+                },
+                "-" => Mode::Stdin,
+                _ if arg.starts_with('-') => {
+                    return Err(UsageError {
+                        message: format!("Unknown option '{}'", arg),
+                    });
+                },
+                _ => Mode::File(arg),
+            };
+
+            if next_mode == Mode::Help {
+                return Ok(Options { mode: Mode::Help, error_format, engine, max_call_depth, stack_size, max_steps, timeout });
+            }
+
+            if mode.is_some() {
+                return Err(UsageError {
+                    message: String::from("Only one script, '-e' code or '-' can be given"),
+                });
+            }
+
+            mode = Some(next_mode);
+        }
+
+        let mode = match mode {
+            Some(mode) => mode,
+            None if stdin_is_terminal => Mode::Repl,
+            None => Mode::Stdin,
+        };
+
+        Ok(Options { mode, error_format, engine, max_call_depth, stack_size, max_steps, timeout })
+    }
+
+    fn positive(name: &str, value: &str) -> Result<usize, UsageError> {
+        match value.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number),
+            _ => Err(UsageError {
+                message: format!("Invalid {} '{}'", name, value),
+            }),
+        }
+    }
+
+    /// Value of option `name` when `arg` is it, given as `name=value` or
+    /// as the next argument
+    fn value_of<I: Iterator<Item = String>>(name: &str, arg: &str, args: &mut I) -> Result<Option<String>, UsageError> {
+        if let Some(value) = arg.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            return Ok(Some(value.to_string()));
+        }
+
+        if arg != name {
+            return Ok(None);
+        }
+
+        match args.next() {
+            Some(value) => Ok(Some(value)),
+            None => Err(UsageError {
+                message: format!("Missing value after '{}'", name),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], stdin_is_terminal: bool) -> Result<Options, UsageError> {
+        Options::parse(args.iter().map(|arg| arg.to_string()), stdin_is_terminal)
+    }
+
+    #[test]
+    fn parse_modes() {
+        let args_expected: Vec<(Vec<&str>, bool, Mode)> = vec![
+            (vec!["script.lox"], true, Mode::File(String::from("script.lox"))),
+            (vec!["-e", "print 1;"], true, Mode::Eval(String::from("print 1;"))),
+            (vec!["--eval", "print 1;"], false, Mode::Eval(String::from("print 1;"))),
+            (vec!["-"], true, Mode::Stdin),
+            (vec![], true, Mode::Repl),
+            (vec![], false, Mode::Stdin),
+            (vec!["script.lox", "--help"], true, Mode::Help),
+        ];
+
+        for (args, stdin_is_terminal, expected) in args_expected {
+            assert_eq!(parse(&args, stdin_is_terminal).unwrap().mode, expected);
+        }
+    }
+
+    #[test]
+    fn parse_error_format() {
+        let args_expected: Vec<(Vec<&str>, ErrorFormat)> = vec![
+            (vec!["script.lox"], ErrorFormat::Human),
+            (vec!["--error-format=json", "script.lox"], ErrorFormat::Json),
+            (vec!["script.lox", "--error-format", "json"], ErrorFormat::Json),
+            (vec!["--error-format=json", "--error-format=human", "-"], ErrorFormat::Human),
+        ];
+
+        for (args, expected) in args_expected {
+            assert_eq!(parse(&args, true).unwrap().error_format, expected);
+        }
+    }
+
+    #[test]
+    fn parse_engine() {
+        let args_expected: Vec<(Vec<&str>, Engine)> = vec![
+            (vec!["script.lox"], Engine::Tree),
+            (vec!["--engine=vm", "script.lox"], Engine::Vm),
+            (vec!["script.lox", "--engine", "vm"], Engine::Vm),
+            (vec!["--engine", "tree", "-"], Engine::Tree),
+        ];
+
+        for (args, expected) in args_expected {
+            assert_eq!(parse(&args, true).unwrap().engine, expected);
+        }
+    }
+
+    #[test]
+    fn parse_limits() {
+        let options = parse(&["script.lox"], true).unwrap();
+        assert_eq!(options.max_call_depth, DEFAULT_MAX_CALL_DEPTH);
+        assert_eq!(options.stack_size, None);
+
+        let options = parse(&["--max-call-depth=5000", "--stack-size", "256", "script.lox"], true).unwrap();
+        assert_eq!(options.max_call_depth, 5000);
+        assert_eq!(options.stack_size, Some(256 * 1024 * 1024));
+
+        let options = parse(&["--max-steps", "1000000", "--timeout=250", "script.lox"], true).unwrap();
+        assert_eq!(options.max_steps, Some(1_000_000));
+        assert_eq!(options.timeout, Some(250));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let args_list: Vec<Vec<&str>> = vec![
+            vec!["-e"],
+            vec!["--unknown"],
+            vec!["a.lox", "b.lox"],
+            vec!["-e", "print 1;", "a.lox"],
+            vec!["--error-format=xml", "a.lox"],
+            vec!["a.lox", "--error-format"],
+            vec!["--engine=jit", "a.lox"],
+            vec!["a.lox", "--engine"],
+            vec!["--max-call-depth=0", "a.lox"],
+            vec!["--stack-size", "big", "a.lox"],
+            vec!["--stack-size", "18446744073709551615", "a.lox"],
+            vec!["--max-steps=-1", "a.lox"],
+            vec!["--timeout", "1s", "a.lox"],
+        ];
+
+        for args in args_list {
+            assert!(parse(&args, true).is_err());
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::scanner::token::Token;
+use crate::value::LValue;
+use crate::vistor::ast_printer::AstPrinter;
+use super::{RunError, Runner, EXIT_IO_ERROR, EXIT_OK};
+use super::interrupt;
+
+const HISTORY_FILE: &str = ".craft_interpreter_history";
+
+// Name errors of the prompt are reported under
+const REPL_FILE_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+:tokens <code>   Print the tokens of the code
+:ast <code>      Print the syntax tree of the code
+:env             Print the global variables, without builtins
+:load <file>     Run a file in the current session
+:history         Print the input history
+:reset           Forget every definition
+:help            Print this help message
+:quit            Leave the prompt
+
+Ctrl-C stops the entry being run, Ctrl-D leaves the prompt";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feed {
+    Done,
+    NeedMore,
+    Quit,
+}
+
+pub struct Repl {
+    runner: Runner,
+    // Source of the entry being typed, which may span several lines
+    buffer: String,
+    // Line number the next entry starts at, every entry continues the
+    // numbering so that resolved tokens of different entries never collide
+    line: usize,
+    // Every entry run so far, so that errors can quote any of their lines
+    transcript: String,
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+}
+
+impl Repl {
+    /// Prompt running entries with `runner`, which keeps its configuration
+    /// across `:reset`
+    pub fn new(runner: Runner, history_path: Option<PathBuf>) -> Repl {
+        let history = history_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+
+        Repl {
+            runner,
+            buffer: String::new(),
+            line: 1,
+            transcript: String::new(),
+            history,
+            history_path,
+        }
+    }
+
+    pub fn default_history_path() -> Option<PathBuf> {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+    }
+
+    pub fn run(&mut self) -> i32 {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut stdout = io::stdout();
+        let mut prompt = "> ";
+
+        // Ctrl-C stops the running entry rather than the whole session
+        interrupt::cancel_on_interrupt(self.runner.interpreter.budget.cancel_handle());
+
+        loop {
+            print!("{}", prompt);
+            if stdout.flush().is_err() {
+                return EXIT_IO_ERROR;
+            }
+
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(error)) => {
+                    eprintln!("Failed to read input: {}", error);
+                    return EXIT_IO_ERROR;
+                },
+                None => {
+                    println!();
+                    return EXIT_OK;
+                },
+            };
+
+            // Forget any Ctrl-C pressed while nothing was running
+            self.runner.interpreter.budget.resume();
+
+            prompt = match self.feed(&line, &mut stdout) {
+                Ok(Feed::Done) => "> ",
+                Ok(Feed::NeedMore) => "... ",
+                Ok(Feed::Quit) => return EXIT_OK,
+                Err(_) => return EXIT_IO_ERROR,
+            };
+        }
+    }
+
+    /// Handle one line of input. Values of bare expressions and the output of
+    /// meta-commands are written to `out`, errors go to stderr.
+    pub fn feed(&mut self, line: &str, out: &mut dyn Write) -> io::Result<Feed> {
+        if self.buffer.is_empty() && line.trim_start().starts_with(':') {
+            self.record_history(line);
+            return self.meta_command(line.trim(), out);
+        }
+
+        // A blank line while waiting for more input gives up on completing it
+        let force = !self.buffer.is_empty() && line.trim().is_empty();
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+
+        if self.buffer.trim().is_empty() {
+            self.buffer.clear();
+            return Ok(Feed::Done);
+        }
+
+        let (tokens, scan_errors) = Scanner::with_start_line(self.buffer.clone(), self.line).scan_tokens_recover();
+
+        let incomplete = if scan_errors.is_empty() {
+            Repl::is_incomplete(&tokens)
+        } else {
+            scan_errors.iter().all(Scanner::is_unterminated)
+        };
+
+        if incomplete && !force {
+            return Ok(Feed::NeedMore);
+        }
+
+        self.finish_entry();
+
+        if scan_errors.is_empty() && Parser::new(tokens.clone()).expression_entry().is_ok() {
+            match self.runner.evaluate_tokens(tokens) {
+                Ok(value) => writeln!(out, "{}", value.repr())?,
+                Err(error) => self.report(&error),
+            }
+        } else if let Err(error) = self.runner.run_tokens(tokens, scan_errors) {
+            self.report(&error);
+        }
+
+        Ok(Feed::Done)
+    }
+
+    fn is_incomplete(tokens: &[Token]) -> bool {
+        if Parser::new(tokens.to_vec()).expression_entry().is_ok() {
+            return false;
+        }
+
+        match Parser::new(tokens.to_vec()).parser() {
+            Ok(_) => false,
+            // Errors at the end are all caused by the missing part
+            Err(errors) => errors.iter().all(|error| error.is_at_eof()),
+        }
+    }
+
+    fn finish_entry(&mut self) {
+        let entry = std::mem::take(&mut self.buffer);
+        self.line += entry.lines().count();
+        self.transcript.push_str(&entry);
+
+        for line in entry.lines() {
+            self.record_history(line);
+        }
+    }
+
+    fn report(&self, error: &RunError) {
+        self.report_in(error, REPL_FILE_NAME, &self.transcript);
+    }
+
+    // Errors go to stderr in the format the runner was set up with
+    fn report_in(&self, error: &RunError, file_name: &str, source: &str) {
+        eprint!("{}", error.render_as(self.runner.error_format, file_name, source));
+    }
+
+    fn record_history(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+
+        self.history.push(line.to_string());
+
+        if let Some(path) = &self.history_path {
+            let file = OpenOptions::new().create(true).append(true).open(path);
+            // History is a convenience, failing to save it must not stop the session
+            if let Ok(mut file) = file {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn meta_command(&mut self, command: &str, out: &mut dyn Write) -> io::Result<Feed> {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+
+        match name {
+            ":tokens" => {
+                let (tokens, scan_errors) = Scanner::new(argument.to_string()).scan_tokens_recover();
+
+                for token in tokens {
+                    write!(out, "{:?}", token)?;
+                }
+
+                if !scan_errors.is_empty() {
+                    self.report_in(&RunError::Syntax(scan_errors, Vec::new()), REPL_FILE_NAME, argument);
+                }
+            },
+            ":ast" => {
+                let tokens = match Scanner::new(argument.to_string()).scan_tokens() {
+                    Ok(tokens) => tokens,
+                    Err(errors) => {
+                        self.report_in(&RunError::Syntax(errors, Vec::new()), REPL_FILE_NAME, argument);
+                        return Ok(Feed::Done);
+                    },
+                };
+
+                let mut printer = AstPrinter::new();
+
+                if let Ok(expr) = Parser::new(tokens.clone()).expression_entry() {
+                    writeln!(out, "{}", printer.print(expr))?;
+                } else {
+                    match Parser::new(tokens).parser() {
+                        Ok(statements) => {
+                            for statement in statements {
+                                writeln!(out, "{}", printer.print_stmt(&statement))?;
+                            }
+                        },
+                        Err(errors) => {
+                            self.report_in(&RunError::Syntax(Vec::new(), errors), REPL_FILE_NAME, argument);
+                        },
+                    }
+                }
+            },
+            ":env" => {
+                let globals = &self.runner.interpreter.globals;
+                // Builtins are always there, only show what the session defined
+                let mut variables: Vec<_> = globals
+                    .iter()
+                    .filter(|(_, value)| !matches!(value, LValue::NativeFunction(_)))
+                    .collect();
+                variables.sort_by_key(|(name, _)| *name);
+
+                for (name, value) in variables {
+                    writeln!(out, "{} = {}", name, value.repr())?;
+                }
+            },
+            ":load" => {
+                match fs::read_to_string(argument) {
+                    Ok(source) => {
+                        let (tokens, scan_errors) = Scanner::with_start_line(source.clone(), self.line)
+                            .scan_tokens_recover();
+                        self.line += source.lines().count();
+
+                        if let Err(error) = self.runner.run_tokens(tokens, scan_errors) {
+                            // Line numbers continue the session rather than
+                            // the file, the file's lines are quoted after the
+                            // earlier entries as the prompt's own
+                            let transcript = format!("{}{}", self.transcript, source);
+                            self.report_in(&error, REPL_FILE_NAME, &transcript);
+                        }
+
+                        self.transcript.push_str(&source);
+                        if !source.ends_with('\n') {
+                            self.transcript.push('\n');
+                        }
+                    },
+                    Err(error) => eprintln!("Could not read file '{}': {}", argument, error),
+                }
+            },
+            ":history" => {
+                for (index, line) in self.history.iter().enumerate() {
+                    writeln!(out, "{:>4}  {}", index + 1, line)?;
+                }
+            },
+            ":reset" => {
+                self.runner.reset();
+                self.line = 1;
+                self.transcript.clear();
+            },
+            ":help" => writeln!(out, "{}", HELP)?,
+            ":quit" | ":exit" => return Ok(Feed::Quit),
+            _ => eprintln!("Unknown command '{}', try :help", name),
+        }
+
+        Ok(Feed::Done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::options::{Engine, ErrorFormat, Options};
+
+    fn feed_all(repl: &mut Repl, lines: &[&str]) -> (Vec<Feed>, String) {
+        let mut out: Vec<u8> = Vec::new();
+        let feeds = lines
+            .iter()
+            .map(|line| repl.feed(line, &mut out).unwrap())
+            .collect();
+
+        (feeds, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn repl_keeps_state() {
+        for engine in [Engine::Tree, Engine::Vm] {
+            let mut runner = Runner::new();
+            runner.engine = engine;
+            let mut repl = Repl::new(runner, None);
+            let (_, output) = feed_all(&mut repl, &[
+                "var a = 1;",
+                "fun add(x) { return x + a; }",
+                "{ var b = 2; a = a + b; }",
+                "add(10)",
+                "lambda (x) => x * a",
+                "[add(1), {\"a\": a}]",
+            ]);
+
+            assert_eq!(output, "13\n<fn lambda>\n[4, {\"a\": 3}]\n");
+        }
+    }
+
+    #[test]
+    fn repl_multiple_line() {
+        let mut repl = Repl::new(Runner::new(), None);
+        let (feeds, output) = feed_all(&mut repl, &[
+            "fun count(n) {",
+            "    var i = 0;",
+            "    while (i < n) i = i + 1;",
+            "    return i;",
+            "}",
+            "var b = count(3)",
+            ";",
+            "b",
+        ]);
+
+        assert_eq!(feeds, vec![
+            Feed::NeedMore,
+            Feed::NeedMore,
+            Feed::NeedMore,
+            Feed::NeedMore,
+            Feed::Done,
+            Feed::NeedMore,
+            Feed::Done,
+            Feed::Done,
+        ]);
+        assert_eq!(output, "3\n");
+    }
+
+    #[test]
+    fn repl_meta_command() {
+        let mut repl = Repl::new(Runner::new(), None);
+        let (feeds, output) = feed_all(&mut repl, &[
+            "var b = 2;",
+            "var a = \"one\";",
+            ":env",
+            ":ast print 1 + 2;",
+            ":reset",
+            ":env",
+            ":quit",
+        ]);
+
+        assert_eq!(feeds.last(), Some(&Feed::Quit));
+        assert_eq!(output, "a = \"one\"\nb = 2\n(print (+ 1 2))\n");
+    }
+
+    #[test]
+    fn repl_open_comment() {
+        let mut repl = Repl::new(Runner::new(), None);
+        let (feeds, output) = feed_all(&mut repl, &[
+            "/* one /* two */",
+            "*/ 1 + 2",
+        ]);
+
+        assert_eq!(feeds, vec![Feed::NeedMore, Feed::Done]);
+        assert_eq!(output, "3\n");
+    }
+
+    #[test]
+    fn repl_keeps_options() {
+        let options = Options::parse(
+            ["--error-format=json", "--max-call-depth", "5", "--max-steps", "100"].map(String::from),
+            true,
+        ).unwrap();
+        let mut repl = Repl::new(Runner::with_options(&options), None);
+        let (_, output) = feed_all(&mut repl, &[
+            "fun r(n) { return r(n + 1); }",
+            ":reset",
+            "var a = 1;",
+            "a",
+        ]);
+
+        assert_eq!(output, "1\n");
+        assert_eq!(repl.runner.error_format, ErrorFormat::Json);
+        assert_eq!(repl.runner.interpreter.max_call_depth, 5);
+        assert_eq!(repl.runner.interpreter.budget.max_steps, Some(100));
+        assert!(matches!(
+            repl.runner.run(String::from("fun r(n) { return r(n + 1); } r(0);")),
+            Err(RunError::Runtime(_, _)),
+        ));
+    }
+
+    #[test]
+    fn repl_load_file() {
+        let path = env::temp_dir().join(format!("craft_interpreter_load_{}.lox", std::process::id()));
+        fs::write(&path, "var loaded = 1;\nfun twice(x) { return x * 2; }\n").unwrap();
+
+        let mut repl = Repl::new(Runner::new(), None);
+        let (_, output) = feed_all(&mut repl, &[
+            "var a = 1;",
+            &format!(":load {}", path.display()),
+            "twice(loaded + a)",
+        ]);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(output, "4\n");
+        assert_eq!(repl.line, 5);
+        assert_eq!(repl.transcript, "var a = 1;\nvar loaded = 1;\nfun twice(x) { return x * 2; }\ntwice(loaded + a)\n");
+    }
+}
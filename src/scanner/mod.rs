@@ -1,3 +1,5 @@
+use std::ops::Range;
+use crate::diagnostic::{Diagnostic, Phase, Span};
 use self::token::{
     Token,
     Literal,
@@ -7,54 +9,138 @@ use self::token::{
 
 pub mod token;
 
+const UNTERMINATED_STRING: &str = "Unterminated string!";
+const UNTERMINATED_COMMENT: &str = "Unterminated block comment";
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct ScannerError {
     pub reason: String,
     pub line: usize,
+    pub col: usize,
+    // Byte range of the offending text in the source
+    pub span: Range<usize>,
+    // Position one past the end of that text
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl From<&ScannerError> for Diagnostic {
+    fn from(error: &ScannerError) -> Diagnostic {
+        let span = Span {
+            line: error.line,
+            col: error.col,
+            end_line: error.end_line,
+            end_col: error.end_col,
+        };
+
+        if error.reason == UNTERMINATED_STRING {
+            Diagnostic::error(Phase::Scan, "E0002", error.reason.clone())
+                .with_span(span)
+                .with_label(String::from("string starts here"))
+                .with_note(String::from("add a closing '\"'"))
+        } else if error.reason == UNTERMINATED_COMMENT {
+            Diagnostic::error(Phase::Scan, "E0003", error.reason.clone())
+                .with_span(span)
+                .with_label(String::from("comment starts here"))
+                .with_note(String::from("add a closing '*/'"))
+        } else {
+            Diagnostic::error(Phase::Scan, "E0001", error.reason.clone())
+                .with_span(span)
+        }
+    }
+}
+
+/// Opening quote of a string and the braces opened in the interpolated
+/// expression being scanned inside it
+#[derive(Clone, Copy)]
+struct OpenString {
+    start: usize,
+    line: usize,
+    col: usize,
+    braces: usize,
 }
 
 pub struct Scanner {
-    pub source: Vec<u8>,
+    pub source: String,
     pub tokens: Vec<Token>,
+    pub errors: Vec<ScannerError>,
     start: usize,
     current: usize,
     line: usize,
     col: usize,
-    error: Option<ScannerError>,
+    // Position of the first character of the token being scanned
+    start_line: usize,
+    start_col: usize,
+    // Strings whose interpolated expression is being scanned, innermost last
+    interpolations: Vec<OpenString>,
 }
 
 impl Scanner {
     pub fn new(src: String) -> Scanner {
         Scanner {
-            source: src.into_bytes(),
+            source: src,
             tokens: Vec::new(),
+            errors: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
             col: 0,
-            error: None,
+            start_line: 1,
+            start_col: 1,
+            interpolations: Vec::new(),
+        }
+    }
+
+    /// Scanner for source that continues a previous one, such as the next
+    /// entry of the prompt, so that tokens keep unique line numbers
+    pub fn with_start_line(src: String, line: usize) -> Scanner {
+        Scanner {
+            line,
+            ..Scanner::new(src)
+        }
+    }
+
+    /// Whether more source could fix the error, as for a string or a
+    /// block comment left open
+    pub fn is_unterminated(error: &ScannerError) -> bool {
+        error.reason == UNTERMINATED_STRING || error.reason == UNTERMINATED_COMMENT
+    }
+
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<ScannerError>> {
+        let (tokens, errors) = self.scan_tokens_recover();
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, ScannerError> {
-        while !self.done() {
+    /// Scan the whole source, turning lexical errors into `TokenType::Error`
+    /// tokens. Returns the tokens along with every error found on the way.
+    pub fn scan_tokens_recover(&mut self) -> (Vec<Token>, Vec<ScannerError>) {
+        while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_col = self.col + 1;
             self.scan_token();
         }
 
-        match &self.error {
-            Some(error) => Err(error.clone()),
-            None => {
-                self.tokens.push(Token {
-                    typee: TokenType::Eof,
-                    lexeme: Vec::new(),
-                    literal: None,
-                    line: self.line,
-                    col: self.col + 1,
-                });
-                Ok(self.tokens.to_vec())
-            }
+        if let Some(open) = self.interpolations.pop() {
+            let error = self.new_error(String::from(UNTERMINATED_STRING), open.line, open.col, open.start..self.current);
+            self.errors.push(error);
+            self.interpolations.clear();
         }
+
+        self.tokens.push(Token {
+            typee: TokenType::Eof,
+            lexeme: Vec::new(),
+            literal: None,
+            line: self.line,
+            col: self.col + 1,
+        });
+
+        (self.tokens.to_vec(), self.errors.to_vec())
     }
 
     fn scan_token(&mut self) {
@@ -63,16 +149,48 @@ impl Scanner {
         match c {
             '(' => self.add_token(TokenType::LeftParen, None),
             ')' => self.add_token(TokenType::RightParen, None),
-            '{' => self.add_token(TokenType::LeftBrace, None),
-            '}' => self.add_token(TokenType::RightBrace, None),
+            '{' => {
+                if let Some(open) = self.interpolations.last_mut() {
+                    open.braces += 1;
+                }
+                self.add_token(TokenType::LeftBrace, None)
+            },
+            '}' => match self.interpolations.pop() {
+                // Closes the interpolated expression, the string goes on
+                Some(open) if open.braces == 0 => self.string(open),
+                Some(mut open) => {
+                    open.braces -= 1;
+                    self.interpolations.push(open);
+                    self.add_token(TokenType::RightBrace, None)
+                },
+                None => self.add_token(TokenType::RightBrace, None),
+            },
             '[' => self.add_token(TokenType::LeftBracket, None),
             ']' => self.add_token(TokenType::RightBracket, None),
             ',' => self.add_token(TokenType::Comma, None),
+            ':' => self.add_token(TokenType::Colon, None),
             '.' => self.add_token(TokenType::Dot, None),
             '-' => self.add_token(TokenType::Minus, None),
             '+' => self.add_token(TokenType::Plus, None),
             ';' => self.add_token(TokenType::Semicolon, None),
-            '*' => self.add_token(TokenType::Star, None),
+            '*' => {
+                if self.match_next('*') {
+                    self.add_token(TokenType::StarStar, None)
+                } else {
+                    self.add_token(TokenType::Star, None)
+                }
+            },
+            '%' => self.add_token(TokenType::Percent, None),
+            '&' => self.add_token(TokenType::Ampersand, None),
+            '|' => self.add_token(TokenType::Pipe, None),
+            '^' => self.add_token(TokenType::Caret, None),
+            '~' => {
+                if self.match_next('/') {
+                    self.add_token(TokenType::TildeSlash, None)
+                } else {
+                    self.add_token(TokenType::Tilde, None)
+                }
+            },
             '!' => {
                 if self.match_next('=') {
                     self.add_token(TokenType::BangEqual, None)
@@ -83,6 +201,8 @@ impl Scanner {
             '=' => {
                 if self.match_next('=') {
                     self.add_token(TokenType::EqualEqual, None)
+                } else if self.match_next('>') {
+                    self.add_token(TokenType::Arrow, None)
                 } else {
                     self.add_token(TokenType::Equal, None)
                 }
@@ -90,6 +210,8 @@ impl Scanner {
             '>' => {
                 if self.match_next('=') {
                     self.add_token(TokenType::GreaterEqual, None)
+                } else if self.match_next('>') {
+                    self.add_token(TokenType::GreaterGreater, None)
                 } else {
                     self.add_token(TokenType::Greater, None)
                 }
@@ -97,15 +219,28 @@ impl Scanner {
             '<' => {
                 if self.match_next('=') {
                     self.add_token(TokenType::LessEqual, None)
+                } else if self.match_next('<') {
+                    self.add_token(TokenType::LessLess, None)
                 } else {
                     self.add_token(TokenType::Less, None)
                 }
             },
             '/' => {
                 if self.match_next('/') {
+                    // `///` but not `////` starts a doc comment
+                    let doc = self.peek() == '/' && self.peek_next() != '/';
+
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+
+                    if doc {
+                        let text = &self.source[self.start + 3..self.current];
+                        let text = text.strip_prefix(' ').unwrap_or(text).trim_end().to_string();
+                        self.add_token(TokenType::DocComment, Some(Literal::Str(text)));
+                    }
+                } else if self.match_next('*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TokenType::Slash, None)
                 }
@@ -117,7 +252,12 @@ impl Scanner {
                 self.col = 0;
             },
             '"' => {
-                self.string();
+                self.string(OpenString {
+                    start: self.start,
+                    line: self.start_line,
+                    col: self.start_col,
+                    braces: 0,
+                });
             },
             _ => {
                 if Scanner::is_digit(c) {
@@ -125,107 +265,285 @@ impl Scanner {
                 } else if Scanner::is_alpha(c) {
                     self.identifier()
                 } else {
-                    self.error = Some(ScannerError{
-                        reason: format!("Unexpected character {}", c),
-                        line: self.line
-                    })
+                    self.error(format!("Unexpected character {}", c));
                 }
             },
         }
     }
 
+    // Positions are byte offsets into the source, columns count characters
     fn advance(&mut self) -> char {
-        let c = char::from(self.source[self.current]);
-        self.current += 1;
+        let c = self.peek();
+        self.current += c.len_utf8();
         self.col += 1;
         c
     }
 
     fn match_next(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-
-        if char::from(self.source[self.current]) != expected {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
-        self.col += 1;
+        self.advance();
         true
     }
 
-    fn peek(&mut self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-
-        char::from(self.source[self.current])
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
-    fn peek_next(&mut self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-
-        char::from(self.source[self.current + 1])
+    fn peek_next(&self) -> char {
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        let text = self.source[self.start..self.current].to_vec();
-        let start_col = self.col - (self.current - self.start - 1);
+        let text = self.source.as_bytes()[self.start..self.current].to_vec();
 
         self.tokens.push(Token {
             typee: token_type,
             lexeme: text,
             literal,
-            line: self.line,
-            col: start_col,
+            line: self.start_line,
+            col: self.start_col,
         })
     }
 
-    fn string(&mut self) {
+    // Skip a block comment, comments opened inside it need closing too
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            // Nothing to stand in for, comments are no tokens
+            if self.is_at_end() {
+                let error = self.new_error(String::from(UNTERMINATED_COMMENT), self.start_line, self.start_col, self.start..self.current);
+                self.errors.push(error);
+                return;
+            }
+
+            match self.advance() {
+                '/' if self.match_next('*') => depth += 1,
+                '*' if self.match_next('/') => depth -= 1,
+                '\n' => {
+                    self.line += 1;
+                    self.col = 0;
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn new_error(&self, reason: String, line: usize, col: usize, span: Range<usize>) -> ScannerError {
+        let end = Span::covering(line, col, &self.source[span.clone()]);
+
+        ScannerError {
+            reason,
+            line,
+            col,
+            span,
+            end_line: end.end_line,
+            end_col: end.end_col,
+        }
+    }
+
+    /// Record an error for the current token and emit an error token in its place
+    fn error(&mut self, reason: String) {
+        let error = self.new_error(reason, self.start_line, self.start_col, self.start..self.current);
+        self.errors.push(error);
+
+        self.add_token(TokenType::Error, None);
+    }
+
+    // Scan up to the closing quote or the next interpolated expression of
+    // the string opened at `open`
+    fn string(&mut self, open: OpenString) {
+        let mut value = String::new();
+        // Invalid escapes are reported on their own, the string becomes an
+        // error token once it is closed
+        let mut escape_errors: Vec<ScannerError> = Vec::new();
+
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.advance();
+                self.advance();
+                self.errors.extend(escape_errors);
+                self.interpolations.push(open);
+                // Keep the segment to leave the parser on track when the
+                // escapes were invalid, the errors still stop the run
+                self.add_token(TokenType::Interpolation, Some(Literal::Str(value)));
+                return;
+            }
+
+            match self.advance() {
+                '\n' => {
+                    self.line += 1;
+                    self.col = 0;
+                    value.push('\n');
+                },
+                '\\' => match self.escape() {
+                    Ok(c) => value.push(c),
+                    Err(error) => escape_errors.push(error),
+                },
+                c => value.push(c),
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            self.error = Some(ScannerError{
-                reason: String::from("Unterminated string!"),
-                line: self.line,
-            });
+            self.start = open.start;
+            self.start_line = open.line;
+            self.start_col = open.col;
+            self.error(String::from(UNTERMINATED_STRING));
             return;
         }
 
         self.advance();
 
-        self.add_token(TokenType::String, Some(Literal::Str(
-            String::from_utf8(self.source[self.start + 1..self.current - 1].to_vec()).unwrap()
-        )));
+        // The end of an interpolated string, the rest of it is already
+        // made of tokens
+        if open.start != self.start {
+            self.errors.extend(escape_errors);
+            self.add_token(TokenType::String, Some(Literal::Str(value)));
+        } else if escape_errors.is_empty() {
+            self.add_token(TokenType::String, Some(Literal::Str(value)));
+        } else {
+            self.errors.extend(escape_errors);
+            self.add_token(TokenType::Error, None);
+        }
     }
 
-    fn number(&mut self) {
-        while Scanner::is_digit(self.peek()) {
-            self.advance();
+    // Character written by the escape sequence whose backslash was just
+    // consumed
+    fn escape(&mut self) -> Result<char, ScannerError> {
+        let start = self.current - 1;
+        let col = self.col;
+
+        // Line breaks are left for the string to count
+        let escaped = match self.peek() {
+            '\n' => None,
+            _ if self.is_at_end() => None,
+            c => {
+                self.advance();
+                Some(c)
+            },
+        };
+
+        let c = match escaped {
+            Some('n') => Some('\n'),
+            Some('t') => Some('\t'),
+            Some('r') => Some('\r'),
+            Some('0') => Some('\0'),
+            Some('\\') => Some('\\'),
+            Some('"') => Some('"'),
+            Some('\'') => Some('\''),
+            Some('$') => Some('$'),
+            Some('u') => self.unicode_escape(),
+            _ => None,
+        };
+
+        c.ok_or_else(|| {
+            let text = &self.source[start..self.current];
+            let reason = match escaped {
+                Some('u') => format!("Invalid unicode escape '{}', expected '\\u{{...}}' around the hex code point", text),
+                _ => format!("Invalid escape sequence '{}'", text),
+            };
+
+            self.new_error(reason, self.line, col, start..self.current)
+        })
+    }
+
+    // Character of `\u{...}`, after the `u`
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.match_next('{') {
+            return None;
         }
 
-        if self.peek() == '.' && Scanner::is_digit(self.peek_next()) {
+        let digits_start = self.current;
+
+        while self.peek().is_ascii_hexdigit() {
             self.advance();
+        }
+
+        let digits_end = self.current;
+
+        if !self.match_next('}') || !(1..=6).contains(&(digits_end - digits_start)) {
+            return None;
+        }
+
+        u32::from_str_radix(&self.source[digits_start..digits_end], 16).ok().and_then(char::from_u32)
+    }
+
+    fn number(&mut self) {
+        let decimal = !matches!(self.peek(), 'x' | 'X' | 'b' | 'B' | 'o' | 'O')
+            || &self.source[self.start..self.current] != "0";
+        let mut fraction = false;
 
-            while Scanner::is_digit(self.peek()) {
+        // Letters right after the digits belong to the literal, so `0xFG`
+        // or `12px` are reported whole
+        loop {
+            let c = self.peek();
+
+            if Scanner::is_alpha_numberic(c) {
+                let exponent = decimal && matches!(c, 'e' | 'E');
                 self.advance();
+
+                if exponent && matches!(self.peek(), '+' | '-') {
+                    self.advance();
+                }
+            } else if decimal && !fraction && c == '.' && Scanner::is_digit(self.peek_next()) {
+                fraction = true;
+                self.advance();
+            } else {
+                break;
             }
         }
 
-        let val: f64 = String::from_utf8(self.source[self.start..self.current].to_vec())
-            .unwrap()
-            .parse()
-            .unwrap();
+        match Scanner::parse_number(&self.source[self.start..self.current]) {
+            Ok(val) => self.add_token(TokenType::Number, Some(Literal::Number(val))),
+            Err(reason) => self.error(reason),
+        }
+    }
 
-        self.add_token(TokenType::Number, Some(Literal::Number(val)))
+    /// Value of a number literal such as `1_000`, `6.02E23`, `0xFF`, `0b1010`
+    /// or `0o17`. Digit separators only go between two digits.
+    fn parse_number(text: &str) -> Result<f64, String> {
+        let (radix, kind) = match text.get(..2) {
+            Some("0x" | "0X") => (16, "hexadecimal"),
+            Some("0b" | "0B") => (2, "binary"),
+            Some("0o" | "0O") => (8, "octal"),
+            _ => (10, "number"),
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let misplaced_separator = chars.iter().enumerate().any(|(index, c)| {
+            *c == '_' && (index == 0
+                || !chars[index - 1].is_digit(radix)
+                || !chars.get(index + 1).is_some_and(|next| next.is_digit(radix)))
+        });
+
+        if misplaced_separator {
+            return Err(format!("Misplaced digit separator in {} literal '{}'", kind, text));
+        }
+
+        let digits: String = chars.iter().filter(|c| **c != '_').collect();
+
+        if radix != 10 {
+            let digits = &digits[2..];
+
+            if digits.is_empty() {
+                return Err(format!("Expect digits after '{}' in {} literal", &text[..2], kind));
+            }
+
+            return digits.chars()
+                .try_fold(0.0, |value, c| c.to_digit(radix).map(|digit| value * radix as f64 + digit as f64))
+                .ok_or_else(|| format!("Invalid digit in {} literal '{}'", kind, text));
+        }
+
+        if let Some((_, exponent)) = digits.split_once(['e', 'E']) {
+            if !exponent.trim_start_matches(['+', '-']).starts_with(|c: char| c.is_ascii_digit()) {
+                return Err(format!("Expect digits in the exponent of number literal '{}'", text));
+            }
+        }
+
+        digits.parse().map_err(|_| format!("Invalid number literal '{}'", text))
     }
 
     fn identifier(&mut self) {
@@ -233,7 +551,7 @@ impl Scanner {
             self.advance();
         }
 
-        let text = String::from_utf8(self.source[self.start..self.current].to_vec()).unwrap();
+        let text = self.source[self.start..self.current].to_string();
         let token_type = match RESERVED_KEYWORD.get(text.as_str()) {
             Some(reserved_token_type) => *reserved_token_type,
             None => TokenType::Identifier,
@@ -249,22 +567,17 @@ impl Scanner {
         self.current >= self.source.len()
     }
 
-    fn done(&self) -> bool {
-        self.is_at_end() || self.error.is_some()
-    }
-
     fn is_digit(c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
+    // Identifiers may use letters of any script
     fn is_alpha(c: char) -> bool {
-        (c >= 'a' && c <= 'z') ||
-        (c >= 'A' && c <= 'Z') ||
-        c == '_'
+        c.is_alphabetic() || c == '_'
     }
 
     fn is_alpha_numberic(c: char) -> bool {
-        return Scanner::is_alpha(c) || Scanner::is_digit(c)
+        Scanner::is_alpha(c) || c.is_numeric()
     }
 }
 
@@ -290,7 +603,8 @@ mod tests {
 
     #[test]
     fn unit_single_character_token() {
-        let test_source: String = String::from("(){}[],.-+;/*");
+        // `/*` would open a comment
+        let test_source: String = String::from("(){}[],.-+;*/:");
         let expected_tokens: Vec<Token> = vec![
             (
                 TokenType::LeftParen,
@@ -370,25 +684,32 @@ mod tests {
                 11,
             ),
             (
-                TokenType::Slash,
-                "/",
+                TokenType::Star,
+                "*",
                 None,
                 1,
                 12,
             ),
             (
-                TokenType::Star,
-                "*",
+                TokenType::Slash,
+                "/",
                 None,
                 1,
                 13,
             ),
+            (
+                TokenType::Colon,
+                ":",
+                None,
+                1,
+                14,
+            ),
             (
                 TokenType::Eof,
                 "",
                 None,
                 1,
-                14,
+                15,
             ),
         ].into_iter()
         .map(|(t, le, li, line, col)| create_token(t, le, li, line, col))
@@ -402,7 +723,7 @@ mod tests {
 
     #[test]
     fn unit_one_or_two_character_token() {
-        let test_source: String = String::from("! != = == > >= < <=");
+        let test_source: String = String::from("! != = == > >= < <= =>");
         let expected_tokens: Vec<Token> = vec![
             (
                 TokenType::Bang,
@@ -460,12 +781,19 @@ mod tests {
                 1,
                 18,
             ),
+            (
+                TokenType::Arrow,
+                "=>",
+                None,
+                1,
+                21,
+            ),
             (
                 TokenType::Eof,
                 "",
                 None,
                 1,
-                20,
+                23,
             ),
         ].into_iter()
         .map(|(t, le, li, line, col)| create_token(t, le, li, line, col))
@@ -477,6 +805,32 @@ mod tests {
         assert_eq!(tokens, expected_tokens);
     }
 
+    #[test]
+    fn operator_tokens() {
+        let source = String::from("% ** * ~/ ~ & | ^ << >> <= >=");
+        let expected_tokens: Vec<Token> = vec![
+            (TokenType::Percent, "%", 1),
+            (TokenType::StarStar, "**", 3),
+            (TokenType::Star, "*", 6),
+            (TokenType::TildeSlash, "~/", 8),
+            (TokenType::Tilde, "~", 11),
+            (TokenType::Ampersand, "&", 13),
+            (TokenType::Pipe, "|", 15),
+            (TokenType::Caret, "^", 17),
+            (TokenType::LessLess, "<<", 19),
+            (TokenType::GreaterGreater, ">>", 22),
+            (TokenType::LessEqual, "<=", 25),
+            (TokenType::GreaterEqual, ">=", 28),
+            (TokenType::Eof, "", 30),
+        ].into_iter()
+        .map(|(t, le, col)| create_token(t, le, None, 1, col))
+        .collect();
+
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+
+        assert_eq!(tokens, expected_tokens);
+    }
+
     #[test]
     fn unit_string_literals() {
         let source: String = String::from("\"This is a test\"");
@@ -723,10 +1077,14 @@ mod tests {
     fn error_unterminated_string() {
         let source: String = String::from("\"Unterminated error");
 
-        let expected_error = ScannerError{
+        let expected_error = vec![ScannerError{
             reason: String::from("Unterminated string!"),
             line: 1,
-        };
+            col: 1,
+            span: 0..19,
+            end_line: 1,
+            end_col: 20,
+        }];
 
         let mut scanner = Scanner::new(source);
         let error = scanner.scan_tokens().unwrap_err();
@@ -738,14 +1096,347 @@ mod tests {
     fn error_unexpected_char() {
         let source: String = String::from("$a");
 
-        let expected_error = ScannerError{
+        let expected_error = vec![ScannerError{
             reason: String::from("Unexpected character $"),
             line: 1,
-        };
+            col: 1,
+            span: 0..1,
+            end_line: 1,
+            end_col: 2,
+        }];
 
         let mut scanner = Scanner::new(source);
         let error = scanner.scan_tokens().unwrap_err();
 
         assert_eq!(error, expected_error);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn error_keep_scanning() {
+        let source: String = String::from("a $ b;\n  # \"c\" €");
+        let expected_tokens: Vec<Token> = vec![
+            (
+                TokenType::Identifier,
+                "a",
+                Some(Literal::Identifier(String::from("a"))),
+                1,
+                1,
+            ),
+            (
+                TokenType::Error,
+                "$",
+                None,
+                1,
+                3,
+            ),
+            (
+                TokenType::Identifier,
+                "b",
+                Some(Literal::Identifier(String::from("b"))),
+                1,
+                5,
+            ),
+            (
+                TokenType::Semicolon,
+                ";",
+                None,
+                1,
+                6,
+            ),
+            (
+                TokenType::Error,
+                "#",
+                None,
+                2,
+                3,
+            ),
+            (
+                TokenType::String,
+                "\"c\"",
+                Some(Literal::Str(String::from("c"))),
+                2,
+                5,
+            ),
+            (
+                TokenType::Error,
+                "€",
+                None,
+                2,
+                9,
+            ),
+            (
+                TokenType::Eof,
+                "",
+                None,
+                2,
+                10,
+            ),
+        ].into_iter()
+        .map(|(t, le, li, line, col)| create_token(t, le, li, line, col))
+        .collect();
+
+        let expected_errors = vec![
+            ScannerError {
+                reason: String::from("Unexpected character $"),
+                line: 1,
+                col: 3,
+                span: 2..3,
+                end_line: 1,
+                end_col: 4,
+            },
+            ScannerError {
+                reason: String::from("Unexpected character #"),
+                line: 2,
+                col: 3,
+                span: 9..10,
+                end_line: 2,
+                end_col: 4,
+            },
+            ScannerError {
+                reason: String::from("Unexpected character €"),
+                line: 2,
+                col: 9,
+                span: 15..18,
+                end_line: 2,
+                end_col: 10,
+            },
+        ];
+
+        let mut scanner = Scanner::new(source);
+        let (tokens, errors) = scanner.scan_tokens_recover();
+
+        assert_eq!(tokens, expected_tokens);
+        assert_eq!(errors, expected_errors);
+    }
+
+    #[test]
+    fn multiple_line_string() {
+        let source: String = String::from("\"a\nb\" c");
+        let expected_tokens: Vec<Token> = vec![
+            (
+                TokenType::String,
+                "\"a\nb\"",
+                Some(Literal::Str(String::from("a\nb"))),
+                1,
+                1,
+            ),
+            (
+                TokenType::Identifier,
+                "c",
+                Some(Literal::Identifier(String::from("c"))),
+                2,
+                4,
+            ),
+            (
+                TokenType::Eof,
+                "",
+                None,
+                2,
+                5,
+            ),
+        ].into_iter()
+        .map(|(t, le, li, line, col)| create_token(t, le, li, line, col))
+        .collect();
+
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    #[test]
+    fn string_escapes() {
+        let source = String::from(r#""tab\there\n\"quoted\" \\ \0 \u{1F600}\u{e9}""#);
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+
+        assert_eq!(tokens[0].literal, Some(Literal::Str(String::from("tab\there\n\"quoted\" \\ \0 \u{1F600}\u{e9}"))));
+        assert_eq!(tokens[1].col, 46);
+    }
+
+    #[test]
+    fn error_invalid_escapes() {
+        let source = String::from(r#"x = "a\q b\u{D800} c\u{1234567}"; y"#);
+        let (tokens, errors) = Scanner::new(source).scan_tokens_recover();
+
+        let reasons: Vec<(&str, usize, Range<usize>)> = errors.iter()
+            .map(|error| (error.reason.as_str(), error.col, error.span.clone()))
+            .collect();
+        assert_eq!(reasons, vec![
+            ("Invalid escape sequence '\\q'", 7, 6..8),
+            ("Invalid unicode escape '\\u{D800}', expected '\\u{...}' around the hex code point", 11, 10..18),
+            ("Invalid unicode escape '\\u{1234567}', expected '\\u{...}' around the hex code point", 21, 20..31),
+        ]);
+
+        // The string is one error token and scanning goes on after it
+        let types: Vec<TokenType> = tokens.iter().map(|token| token.typee).collect();
+        assert_eq!(types, vec![TokenType::Identifier, TokenType::Equal, TokenType::Error, TokenType::Semicolon, TokenType::Identifier, TokenType::Eof]);
+    }
+
+    #[test]
+    fn unicode_identifiers_and_columns() {
+        let source = String::from("var café = \"naïve → ok\"; π $");
+        let (tokens, errors) = Scanner::new(source).scan_tokens_recover();
+
+        let columns: Vec<(TokenType, usize)> = tokens.iter().map(|token| (token.typee, token.col)).collect();
+        assert_eq!(columns, vec![
+            (TokenType::Var, 1),
+            (TokenType::Identifier, 5),
+            (TokenType::Equal, 10),
+            (TokenType::String, 12),
+            (TokenType::Semicolon, 24),
+            (TokenType::Identifier, 26),
+            (TokenType::Error, 28),
+            (TokenType::Eof, 29),
+        ]);
+        assert_eq!(tokens[1].literal, Some(Literal::Identifier(String::from("café"))));
+        assert_eq!(tokens[3].literal, Some(Literal::Str(String::from("naïve → ok"))));
+        assert_eq!(errors[0].col, 28);
+    }
+
+    #[test]
+    fn string_interpolation() {
+        let source = String::from("\"a ${b + {}} c ${\"d${e}\"}\" \"\\${f}\"");
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+
+        let scanned: Vec<(TokenType, String)> = tokens.iter()
+            .map(|token| (token.typee, String::from_utf8(token.lexeme.clone()).unwrap()))
+            .collect();
+        let expected: Vec<(TokenType, &str)> = vec![
+            (TokenType::Interpolation, "\"a ${"),
+            (TokenType::Identifier, "b"),
+            (TokenType::Plus, "+"),
+            (TokenType::LeftBrace, "{"),
+            (TokenType::RightBrace, "}"),
+            (TokenType::Interpolation, "} c ${"),
+            (TokenType::Interpolation, "\"d${"),
+            (TokenType::Identifier, "e"),
+            (TokenType::String, "}\""),
+            (TokenType::String, "}\""),
+            (TokenType::String, "\"\\${f}\""),
+            (TokenType::Eof, ""),
+        ];
+        assert_eq!(scanned, expected.into_iter().map(|(typee, lexeme)| (typee, lexeme.to_string())).collect::<Vec<_>>());
+
+        assert_eq!(tokens[5].literal, Some(Literal::Str(String::from(" c "))));
+        assert_eq!(tokens[10].literal, Some(Literal::Str(String::from("${f}"))));
+    }
+
+    #[test]
+    fn error_unterminated_interpolation() {
+        let (_, errors) = Scanner::new(String::from("x = \"a ${b\n+ c")).scan_tokens_recover();
+
+        assert_eq!(errors, vec![ScannerError {
+            reason: String::from(UNTERMINATED_STRING),
+            line: 1,
+            col: 5,
+            span: 4..14,
+            end_line: 2,
+            end_col: 4,
+        }]);
+        assert!(Scanner::is_unterminated(&errors[0]));
+    }
+
+    #[test]
+    fn number_forms() {
+        let source_expected: Vec<(&str, f64)> = vec![
+            ("0xFF", 255.0),
+            ("0Xab", 171.0),
+            ("0b1010", 10.0),
+            ("0o17", 15.0),
+            ("1e-9", 1e-9),
+            ("6.02E23", 6.02e23),
+            ("2.5e+3", 2500.0),
+            ("1_000_000", 1_000_000.0),
+            ("0xFFFF_FFFF", 4_294_967_295.0),
+            ("1_0.2_5", 10.25),
+            ("007", 7.0),
+        ];
+
+        for (source, expected) in source_expected {
+            let tokens = Scanner::new(String::from(source)).scan_tokens().unwrap();
+            assert_eq!(tokens[0].literal, Some(Literal::Number(expected)), "{}", source);
+            assert_eq!(tokens[1].typee, TokenType::Eof, "{}", source);
+        }
+
+        // A hexadecimal `e` is a digit, not an exponent
+        let tokens = Scanner::new(String::from("0x1e+1")).scan_tokens().unwrap();
+        let types: Vec<TokenType> = tokens.iter().map(|token| token.typee).collect();
+        assert_eq!(types, vec![TokenType::Number, TokenType::Plus, TokenType::Number, TokenType::Eof]);
+    }
+
+    #[test]
+    fn error_malformed_numbers() {
+        let source_expected: Vec<(&str, &str)> = vec![
+            ("0x", "Expect digits after '0x' in hexadecimal literal"),
+            ("0b", "Expect digits after '0b' in binary literal"),
+            ("1e", "Expect digits in the exponent of number literal '1e'"),
+            ("1.5E-", "Expect digits in the exponent of number literal '1.5E-'"),
+            ("1_", "Misplaced digit separator in number literal '1_'"),
+            ("1__0", "Misplaced digit separator in number literal '1__0'"),
+            ("0o_7", "Misplaced digit separator in octal literal '0o_7'"),
+            ("0b102", "Invalid digit in binary literal '0b102'"),
+            ("0xFG", "Invalid digit in hexadecimal literal '0xFG'"),
+            ("12px", "Invalid number literal '12px'"),
+        ];
+
+        for (source, expected) in source_expected {
+            let (tokens, errors) = Scanner::new(format!("{};", source)).scan_tokens_recover();
+            assert_eq!(errors, vec![ScannerError {
+                reason: String::from(expected),
+                line: 1,
+                col: 1,
+                span: 0..source.len(),
+                end_line: 1,
+                end_col: source.len() + 1,
+            }]);
+            assert_eq!(tokens[1].typee, TokenType::Semicolon);
+        }
+    }
+
+    #[test]
+    fn block_comments() {
+        let source = String::from("a /* one /* two\n */ still\n */ b /**/ c");
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+
+        let positions: Vec<(TokenType, usize, usize)> = tokens.iter().map(|token| (token.typee, token.line, token.col)).collect();
+        assert_eq!(positions, vec![
+            (TokenType::Identifier, 1, 1),
+            (TokenType::Identifier, 3, 5),
+            (TokenType::Identifier, 3, 12),
+            (TokenType::Eof, 3, 13),
+        ]);
+    }
+
+    #[test]
+    fn error_unterminated_comment() {
+        let (tokens, errors) = Scanner::new(String::from("a;\n  /* open /* closed */\n")).scan_tokens_recover();
+
+        assert_eq!(errors, vec![ScannerError {
+            reason: String::from(UNTERMINATED_COMMENT),
+            line: 2,
+            col: 3,
+            span: 5..26,
+            end_line: 2,
+            end_col: 23,
+        }]);
+        assert!(Scanner::is_unterminated(&errors[0]));
+        assert_eq!(tokens.last().map(|token| token.typee), Some(TokenType::Eof));
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn doc_comments() {
+        let source = String::from("/// Adds one.\n///\n//// not documentation\nfun");
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+
+        let docs: Vec<(TokenType, Option<Literal>, usize)> = tokens.iter()
+            .map(|token| (token.typee, token.literal.clone(), token.line))
+            .collect();
+        assert_eq!(docs, vec![
+            (TokenType::DocComment, Some(Literal::Str(String::from("Adds one."))), 1),
+            (TokenType::DocComment, Some(Literal::Str(String::new())), 2),
+            (TokenType::Fun, None, 4),
+            (TokenType::Eof, None, 4),
+        ]);
+    }
+}
@@ -11,12 +11,17 @@ pub enum TokenType {
     LeftBracket,
     RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
 
     // One or two character tokens.
     Bang,
@@ -27,10 +32,20 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    Arrow,
+    StarStar,
+    // `~/`, floor division, `//` already starts a comment
+    TildeSlash,
+    Tilde,
+    LessLess,
+    GreaterGreater,
 
     // Literals.
     Identifier,
     String,
+    // Part of a string followed by an interpolated `${...}` expression,
+    // the string goes on after the expression up to a `String` token
+    Interpolation,
     Number,
  
     // Keywords.
@@ -53,7 +68,17 @@ pub enum TokenType {
     Lambda,
     New,
     Extend,
-
+    Break,
+    Continue,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+
+    // Stands in for text the scanner could not make sense of
+    Error,
+    // `///` comment kept for documentation tools, the parser skips it
+    DocComment,
     Eof,
 }
 
@@ -84,6 +109,12 @@ pub static RESERVED_KEYWORD: phf::Map<&'static str, TokenType> = phf_map!{
     "lambda" => TokenType::Lambda,
     "new" => TokenType::New,
     "extend" => TokenType::Extend,
+    "break" => TokenType::Break,
+    "continue" => TokenType::Continue,
+    "throw" => TokenType::Throw,
+    "try" => TokenType::Try,
+    "catch" => TokenType::Catch,
+    "finally" => TokenType::Finally,
 };
 
 #[derive(Clone, PartialOrd)]
@@ -97,9 +128,9 @@ pub struct Token {
 
 impl fmt::Debug for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        writeln!(
             f,
-            "Token {{ type: {:?}, lexeme: \"{}\", literal: {:#?}, line: {:#?}, col: {:#?}}}\n",
+            "Token {{ type: {:?}, lexeme: \"{}\", literal: {:#?}, line: {:#?}, col: {:#?}}}",
             self.typee,
             String::from_utf8(self.lexeme.clone()).unwrap(),
             self.literal,
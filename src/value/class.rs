@@ -1,4 +1,4 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
 use crate::{
     interpreter::Interpreter,
     runner::error::{CommonError, RunTimeError},
@@ -24,6 +24,12 @@ impl PartialOrd for Class {
     }
 }
 
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
 impl Class {
     pub fn new(
         name: String,
@@ -37,24 +43,20 @@ impl Class {
         })
     }
 
-    pub fn to_string(&self) -> String {
-        self.name.clone()
-    }
-
     pub fn find_method(self: Rc<Self>, token: &Token) -> Option<Rc<Function>> {
-        let method_name = String::from_utf8(token.lexeme.to_vec()).unwrap();
+        self.find_method_by_name(&String::from_utf8_lossy(&token.lexeme))
+    }
 
-        if self.methods.contains_key(&method_name) {
-            if let Some(method) = self.methods.get(&method_name) {
-                return Some(method.clone());
-            }
+    pub fn find_method_by_name(&self, method_name: &str) -> Option<Rc<Function>> {
+        if let Some(method) = self.methods.get(method_name) {
+            return Some(method.clone());
         }
 
         if let Some(ref supper_class) = self.supper_class {
-            return supper_class.clone().find_method(token);
+            return supper_class.find_method_by_name(method_name);
         }
 
-        return None;
+        None
     }
 }
 
@@ -70,12 +72,16 @@ impl Class {
             interpreter,
             token,
             arguments,
-        );
+        )?;
         Ok(LValue::ClassInstance(instance))
     }
 
+    /// Arguments taken by `init`, which may be inherited
     pub fn arity(&self) -> usize {
-        return 0;
+        match self.find_method_by_name("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
     }
 }
 
@@ -92,12 +98,18 @@ impl PartialOrd for ClassInstance {
     }
 }
 
+impl fmt::Display for ClassInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
 impl ClassInstance {
     pub fn new(class: Rc<Class>,
         interpreter: &mut Interpreter,
         token: &Token,
         arguments: Vec<super::LValue>,
-    ) -> Rc<ClassInstance> {
+    ) -> Result<Rc<ClassInstance>, RunTimeError> {
         let instance = Rc::new(ClassInstance {
             class: class.clone(),
             fields: RefCell::new(HashMap::new()),
@@ -115,14 +127,10 @@ impl ClassInstance {
         let initializer = class.find_method(&mock_init_token);
 
         if let Some(init_func) = initializer {
-            init_func.bind(instance.clone()).call(interpreter, token, arguments);
+            init_func.bind(instance.clone()).call(interpreter, token, arguments)?;
         }
 
-        return instance;
-    }
-
-    pub fn to_string(&self) -> String {
-        format!("{} instance", self.class.name)
+        Ok(instance)
     }
 
     pub fn get(self: Rc<Self>, name: &Token) -> Result<LValue, RunTimeError>  {
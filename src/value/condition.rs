@@ -10,10 +10,15 @@ impl IsTruthy for LValue {
             LValue::Bool(bool) => *bool,
             LValue::Nil => false,
             LValue::Number(num) => *num != 0.0,
-            LValue::String(str) => (*str).is_empty(),
+            LValue::String(str) => !str.is_empty(),
             LValue::Function(_) => true,
+            LValue::NativeFunction(_) => true,
             LValue::Class(_) => true,
             LValue::ClassInstance(_) => true,
+            LValue::List(list) => !list.is_empty(),
+            LValue::Map(map) => !map.is_empty(),
+            LValue::Exception(_) => true,
+            LValue::Object(_) => true,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,77 @@
+use std::fmt;
+use crate::{
+    runner::error::{CommonError, RunTimeError},
+    scanner::token::Token,
+};
+use super::LValue;
+
+/// Runtime error raised by the interpreter itself, as seen by a `catch`
+/// clause. Throwing it again raises the original error.
+#[derive(PartialEq, PartialOrd)]
+pub struct Exception {
+    pub message: String,
+    // Where the error was raised, if known
+    pub token: Option<Token>,
+}
+
+impl fmt::Debug for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.message)
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Exception {
+    /// Read the `message` or `line` of the error, the line is nil when
+    /// the error has no position
+    pub fn get(&self, name: &Token) -> Result<LValue, RunTimeError> {
+        match name.lexeme.as_slice() {
+            b"message" => Ok(LValue::String(self.message.clone())),
+            b"line" => Ok(match &self.token {
+                Some(token) => LValue::Number(token.line as f64),
+                None => LValue::Nil,
+            }),
+            _ => Err(RunTimeError::Error(CommonError {
+                token: Some(name.clone()),
+                message: format!("Undefined property {}", String::from_utf8_lossy(&name.lexeme)),
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::token::TokenType;
+
+    fn identifier(name: &str, line: usize) -> Token {
+        Token {
+            typee: TokenType::Identifier,
+            lexeme: String::from(name).into_bytes(),
+            literal: None,
+            line,
+            col: 1,
+        }
+    }
+
+    #[test]
+    fn exception_fields() {
+        let exception = Exception {
+            message: String::from("Undefined variable 'a'."),
+            token: Some(identifier("a", 3)),
+        };
+
+        assert_eq!(exception.get(&identifier("message", 1)), Ok(LValue::String(String::from("Undefined variable 'a'."))));
+        assert_eq!(exception.get(&identifier("line", 1)), Ok(LValue::Number(3.0)));
+
+        match exception.get(&identifier("code", 1)) {
+            Err(RunTimeError::Error(error)) => assert_eq!(error.message, "Undefined property code"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -1,21 +1,36 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc};
 
 use crate::{
     environment::Environment,
     grammer::statement::Stmt,
-    interpreter::Interpreter,
+    interpreter::{trace::CallSite, Interpreter},
     runner::error::RunTimeError,
     scanner::token::{Token, TokenType},
 };
 use super::{class::ClassInstance, LValue};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Function {
     pub name: Token,
-    pub params: Vec<Token>,
-    pub body: Stmt, // Block statement
+    pub params: Rc<Vec<Token>>,
+    pub body: Rc<Stmt>, // Block statement
     pub closure: Rc<RefCell<Environment>>,
     pub is_initializer: bool,
+    // Class a method is defined in
+    pub class: Option<String>,
+}
+
+// The closure may hold the function itself, so never print it
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", String::from_utf8_lossy(&self.name.lexeme))
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
 impl PartialEq for Function {
@@ -33,11 +48,9 @@ impl PartialOrd for Function {
 
 impl Function {
     pub fn bind(self: &Rc<Self>, instance: Rc<ClassInstance>) -> Rc<Function> {
-        let mut new_environment = Environment::new();
-        new_environment.define(String::from("this"), LValue::ClassInstance(instance));
-
-        // Add closure for original method closure
-        new_environment.enclosing = Some(self.closure.clone());
+        // Add closure for original method closure, `this` is its only slot
+        let mut new_environment = Environment::with_enclosing(self.closure.clone());
+        new_environment.define(LValue::ClassInstance(instance));
 
         Rc::new(Function {
             name: self.name.clone(),
@@ -45,8 +58,32 @@ impl Function {
             body: self.body.clone(),
             is_initializer: self.is_initializer,
             closure: Rc::new(RefCell::new(new_environment)),
+            class: self.class.clone(),
         })
     }
+
+    /// Name shown in tracebacks, qualified by the class for methods
+    pub fn trace_name(&self) -> String {
+        let name = String::from_utf8_lossy(&self.name.lexeme);
+
+        match &self.class {
+            Some(class) => format!("{}.{}", class, name),
+            None => name.into_owned(),
+        }
+    }
+
+    /// Instance a method is bound to, which is the only slot of its closure
+    fn this_value(&self) -> Result<LValue, RunTimeError> {
+        let this_token = Token {
+            typee: TokenType::This,
+            col: 0,
+            line: 0,
+            lexeme: String::from("this").into_bytes(),
+            literal: None,
+        };
+
+        self.closure.borrow().get_at(0, 0, &this_token)
+    }
 }
 
 pub trait Callable {
@@ -66,49 +103,49 @@ impl Callable for Function {
         token: &Token,
         arguments: Vec<LValue>,
     ) -> Result<LValue, RunTimeError> {
-        let mut environment = Environment::new();
-        environment.enclosing = Some(self.closure.clone());
-
-        for (index, param) in self.params.iter().enumerate() {
-            environment.define(
-                String::from_utf8(param.lexeme.to_vec()).unwrap(),
-                arguments.get(index).unwrap().clone(),
-            );
+        if interpreter.calls.len() >= interpreter.max_call_depth {
+            return Err(interpreter.stack_overflow(&self.trace_name(), token));
+        }
+
+        interpreter.budget.check(Some(token))?;
+
+        // Callers check the arity, so params take the first slots in order
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+
+        for argument in arguments {
+            environment.define(argument);
         }
 
         let environment_ref = Rc::new(RefCell::new(environment));
-        let call_res = interpreter.exectue_with_env(&self.body, environment_ref);
 
-        let init_mock_token = Token {
-            typee: TokenType::Identifier,
-            col: 0,
-            line: 0,
-            lexeme: String::from("this").as_bytes().to_vec(),
-            literal: None,
-        };
+        interpreter.calls.push(CallSite {
+            function: self.trace_name(),
+            line: token.line,
+        });
+
+        let call_res = interpreter.exectue_with_env(&self.body, environment_ref);
 
-        if let Err(e) = call_res {
-            match e {
-                RunTimeError::Error(_) => return Err(e),
-                RunTimeError::Return(val) => {
-                    if self.is_initializer {
-                        // Return this when it is initializer function
-                        return self.closure.borrow_mut().get_at(0, &init_mock_token);
-                    }
-                    return Ok(val);
-                }
-            }
+        if let Err(error) = &call_res {
+            interpreter.record_traceback(error);
         }
 
+        interpreter.calls.pop();
+
+        let return_value = match call_res {
+            Ok(_) => LValue::Nil,
+            Err(RunTimeError::Return(val)) => val,
+            Err(e) => return Err(e),
+        };
+
         // Return this when it is initializer function
         if self.is_initializer {
-            return self.closure.borrow_mut().get_at(0, &init_mock_token);
+            return self.this_value();
         }
 
-        Ok(LValue::Nil)
+        Ok(return_value)
     }
 
     fn arity(&self) -> usize {
-        return 0;
+        self.params.len()
     }
-}
\ No newline at end of file
+}
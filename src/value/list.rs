@@ -0,0 +1,169 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+use crate::{
+    runner::error::{CommonError, RunTimeError},
+    scanner::token::Token,
+};
+use super::LValue;
+
+/// Ordered values, shared and mutated through every reference to the list
+#[derive(Default)]
+pub struct List {
+    pub elements: RefCell<Vec<LValue>>,
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        super::format_once(self.identity(), f, "[...]", |f| write!(f, "{:?}", self.elements.borrow()))
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        super::format_once(self.identity(), f, "[...]", |f| {
+            let elements: Vec<String> = self.elements.borrow().iter().map(LValue::repr).collect();
+            write!(f, "[{}]", elements.join(", "))
+        })
+    }
+}
+
+// Lists are only equal to themselves
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Lists have no order
+impl PartialOrd for List {
+    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
+        None
+    }
+}
+
+impl List {
+    pub fn new(elements: Vec<LValue>) -> Rc<List> {
+        Rc::new(List {
+            elements: RefCell::new(elements),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+
+    /// Read `list[index]`, errors point at the bracket
+    pub fn get(&self, index: &LValue, bracket: &Token) -> Result<LValue, RunTimeError> {
+        let index = self.index(index, false).map_err(|message| List::error(bracket, message))?;
+        Ok(self.elements.borrow()[index].clone())
+    }
+
+    /// Write `list[index] = value`, errors point at the bracket
+    pub fn set(&self, index: &LValue, bracket: &Token, value: LValue) -> Result<(), RunTimeError> {
+        let index = self.index(index, false).map_err(|message| List::error(bracket, message))?;
+        self.elements.borrow_mut()[index] = value;
+        Ok(())
+    }
+
+    pub fn push(&self, value: LValue) {
+        self.elements.borrow_mut().push(value);
+    }
+
+    pub fn pop(&self) -> Option<LValue> {
+        self.elements.borrow_mut().pop()
+    }
+
+    /// Insert before `index`, which may be the length to append
+    pub fn insert(&self, index: &LValue, value: LValue) -> Result<(), String> {
+        let index = self.index(index, true)?;
+        self.elements.borrow_mut().insert(index, value);
+        Ok(())
+    }
+
+    pub fn remove(&self, index: &LValue) -> Result<LValue, String> {
+        let index = self.index(index, false)?;
+        Ok(self.elements.borrow_mut().remove(index))
+    }
+
+    // Indexes are integral numbers within the list, or right after it
+    // when `allow_end` is set
+    fn index(&self, index: &LValue, allow_end: bool) -> Result<usize, String> {
+        let number = match index {
+            LValue::Number(number) if number.fract() == 0.0 => *number,
+            _ => return Err(format!("List index must be an integer, got {}", index.repr())),
+        };
+
+        let len = self.len();
+        let in_bounds = number >= 0.0 && (number < len as f64 || (allow_end && number == len as f64));
+
+        if !in_bounds {
+            return Err(format!("List index {} out of bounds for length {}", number, len));
+        }
+
+        Ok(number as usize)
+    }
+
+    fn error(bracket: &Token, message: String) -> RunTimeError {
+        RunTimeError::Error(CommonError {
+            token: Some(bracket.clone()),
+            message,
+        })
+    }
+
+    fn identity(&self) -> *const () {
+        self as *const List as *const ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_index() {
+        let list = List::new(vec![LValue::Number(1.0), LValue::Number(2.0)]);
+
+        assert_eq!(list.index(&LValue::Number(1.0), false), Ok(1));
+        assert_eq!(list.index(&LValue::Number(2.0), true), Ok(2));
+        assert_eq!(
+            list.index(&LValue::Number(2.0), false),
+            Err(String::from("List index 2 out of bounds for length 2")),
+        );
+        assert_eq!(
+            list.index(&LValue::Number(-1.0), true),
+            Err(String::from("List index -1 out of bounds for length 2")),
+        );
+        assert_eq!(
+            list.index(&LValue::Number(0.5), false),
+            Err(String::from("List index must be an integer, got 0.5")),
+        );
+        assert_eq!(
+            list.index(&LValue::String(String::from("0")), false),
+            Err(String::from("List index must be an integer, got \"0\"")),
+        );
+    }
+
+    #[test]
+    fn list_operations() {
+        let list = List::new(Vec::new());
+        list.push(LValue::Number(1.0));
+        list.insert(&LValue::Number(0.0), LValue::Number(0.0)).unwrap();
+        list.insert(&LValue::Number(2.0), LValue::Number(2.0)).unwrap();
+
+        assert_eq!(list.remove(&LValue::Number(1.0)), Ok(LValue::Number(1.0)));
+        assert_eq!(list.pop(), Some(LValue::Number(2.0)));
+        assert_eq!(*list.elements.borrow(), vec![LValue::Number(0.0)]);
+    }
+
+    #[test]
+    fn list_containing_itself() {
+        let list = List::new(vec![LValue::Number(1.0)]);
+        list.push(LValue::List(list.clone()));
+
+        assert_eq!(list.to_string(), "[1, [...]]");
+        assert_eq!(format!("{:?}", list), "[Number(1.0), List([...])]");
+    }
+}
@@ -0,0 +1,279 @@
+use std::{cell::RefCell, collections::HashMap, fmt, hash::{Hash, Hasher}, rc::Rc};
+use crate::{
+    runner::error::{CommonError, RunTimeError},
+    scanner::token::Token,
+};
+use super::LValue;
+
+/// Value usable as a map key. Equal values give equal keys, so `-0` and
+/// `0` are one key, and all NaNs share one key even though `==` never holds
+/// for NaN. Functions, classes, instances and collections can't be keys.
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Nil,
+}
+
+impl MapKey {
+    pub fn from_value(value: &LValue) -> Result<MapKey, String> {
+        match value {
+            LValue::Number(number) => Ok(MapKey::Number(*number)),
+            LValue::String(string) => Ok(MapKey::String(string.clone())),
+            LValue::Bool(bool) => Ok(MapKey::Bool(*bool)),
+            LValue::Nil => Ok(MapKey::Nil),
+            _ => Err(format!("A {} can't be used as a map key", value.kind())),
+        }
+    }
+
+    pub fn to_value(&self) -> LValue {
+        match self {
+            MapKey::Number(number) => LValue::Number(*number),
+            MapKey::String(string) => LValue::String(string.clone()),
+            MapKey::Bool(bool) => LValue::Bool(*bool),
+            MapKey::Nil => LValue::Nil,
+        }
+    }
+
+    // Zeros of both signs are equal, as are all NaNs so that a NaN key
+    // can be found again
+    fn number_bits(number: f64) -> u64 {
+        if number == 0.0 {
+            0
+        } else if number.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            number.to_bits()
+        }
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (MapKey::Number(a), MapKey::Number(b)) => MapKey::number_bits(*a) == MapKey::number_bits(*b),
+            (MapKey::String(a), MapKey::String(b)) => a == b,
+            (MapKey::Bool(a), MapKey::Bool(b)) => a == b,
+            (MapKey::Nil, MapKey::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            MapKey::Number(number) => MapKey::number_bits(*number).hash(state),
+            MapKey::String(string) => string.hash(state),
+            MapKey::Bool(bool) => bool.hash(state),
+            MapKey::Nil => {},
+        }
+    }
+}
+
+// Entries are kept in insertion order, the index finds them by key
+#[derive(Default)]
+struct Entries {
+    pairs: Vec<(MapKey, LValue)>,
+    index: HashMap<MapKey, usize>,
+}
+
+/// Key value pairs, shared and mutated through every reference to the map
+#[derive(Default)]
+pub struct Map {
+    entries: RefCell<Entries>,
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        super::format_once(self.identity(), f, "{...}", |f| {
+            f.debug_map()
+                .entries(self.entries.borrow().pairs.iter().map(|(key, value)| (key.to_value(), value.clone())))
+                .finish()
+        })
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        super::format_once(self.identity(), f, "{...}", |f| {
+            let pairs: Vec<String> = self.entries.borrow().pairs.iter()
+                .map(|(key, value)| format!("{}: {}", key.to_value().repr(), value.repr()))
+                .collect();
+            write!(f, "{{{}}}", pairs.join(", "))
+        })
+    }
+}
+
+// Maps are only equal to themselves
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Maps have no order
+impl PartialOrd for Map {
+    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
+        None
+    }
+}
+
+impl Map {
+    /// Build a map from pairs, a repeated key keeps its last value
+    pub fn new(pairs: Vec<(MapKey, LValue)>) -> Rc<Map> {
+        let map = Map::default();
+
+        for (key, value) in pairs {
+            map.insert(key, value);
+        }
+
+        Rc::new(map)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().pairs.is_empty()
+    }
+
+    /// Read `map[key]`, errors point at the bracket
+    pub fn get(&self, key: &LValue, bracket: &Token) -> Result<LValue, RunTimeError> {
+        let map_key = MapKey::from_value(key).map_err(|message| Map::error(bracket, message))?;
+        let entries = self.entries.borrow();
+
+        match entries.index.get(&map_key) {
+            Some(position) => Ok(entries.pairs[*position].1.clone()),
+            None => Err(Map::error(bracket, format!("Key {} not found in map", key.repr()))),
+        }
+    }
+
+    /// Write `map[key] = value`, errors point at the bracket
+    pub fn set(&self, key: &LValue, bracket: &Token, value: LValue) -> Result<(), RunTimeError> {
+        let map_key = MapKey::from_value(key).map_err(|message| Map::error(bracket, message))?;
+        self.insert(map_key, value);
+        Ok(())
+    }
+
+    pub fn insert(&self, key: MapKey, value: LValue) {
+        let mut entries = self.entries.borrow_mut();
+
+        match entries.index.get(&key) {
+            Some(position) => {
+                let position = *position;
+                entries.pairs[position].1 = value;
+            },
+            None => {
+                let position = entries.pairs.len();
+                entries.index.insert(key.clone(), position);
+                entries.pairs.push((key, value));
+            },
+        }
+    }
+
+    pub fn has(&self, key: &LValue) -> Result<bool, String> {
+        let map_key = MapKey::from_value(key)?;
+        Ok(self.entries.borrow().index.contains_key(&map_key))
+    }
+
+    /// Remove a key and tell whether it was there
+    pub fn delete(&self, key: &LValue) -> Result<bool, String> {
+        let map_key = MapKey::from_value(key)?;
+        let mut entries = self.entries.borrow_mut();
+
+        let position = match entries.index.remove(&map_key) {
+            Some(position) => position,
+            None => return Ok(false),
+        };
+
+        entries.pairs.remove(position);
+
+        for later in entries.index.values_mut() {
+            if *later > position {
+                *later -= 1;
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn keys(&self) -> Vec<LValue> {
+        self.entries.borrow().pairs.iter().map(|(key, _)| key.to_value()).collect()
+    }
+
+    pub fn values(&self) -> Vec<LValue> {
+        self.entries.borrow().pairs.iter().map(|(_, value)| value.clone()).collect()
+    }
+
+    fn error(bracket: &Token, message: String) -> RunTimeError {
+        RunTimeError::Error(CommonError {
+            token: Some(bracket.clone()),
+            message,
+        })
+    }
+
+    fn identity(&self) -> *const () {
+        self as *const Map as *const ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::token::TokenType;
+
+    fn bracket() -> Token {
+        Token {
+            typee: TokenType::LeftBracket,
+            lexeme: String::from("[").into_bytes(),
+            literal: None,
+            line: 1,
+            col: 2,
+        }
+    }
+
+    #[test]
+    fn map_keys() {
+        assert_eq!(MapKey::from_value(&LValue::Number(-0.0)), Ok(MapKey::Number(0.0)));
+        assert_eq!(MapKey::from_value(&LValue::Number(f64::NAN)), Ok(MapKey::Number(-f64::NAN)));
+        assert_ne!(MapKey::Number(1.0), MapKey::String(String::from("1")));
+        assert_ne!(MapKey::Bool(false), MapKey::Nil);
+        assert_eq!(
+            MapKey::from_value(&LValue::List(crate::value::list::List::new(Vec::new()))),
+            Err(String::from("A list can't be used as a map key")),
+        );
+    }
+
+    #[test]
+    fn map_operations() {
+        let map = Map::new(vec![
+            (MapKey::String(String::from("a")), LValue::Number(1.0)),
+            (MapKey::Number(2.0), LValue::Nil),
+            (MapKey::String(String::from("a")), LValue::Number(3.0)),
+        ]);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&LValue::String(String::from("a")), &bracket()), Ok(LValue::Number(3.0)));
+
+        map.set(&LValue::Bool(true), &bracket(), LValue::Number(4.0)).unwrap();
+        assert_eq!(map.delete(&LValue::String(String::from("a"))), Ok(true));
+        assert_eq!(map.delete(&LValue::String(String::from("a"))), Ok(false));
+        assert_eq!(map.has(&LValue::Number(2.0)), Ok(true));
+
+        assert_eq!(map.keys(), vec![LValue::Number(2.0), LValue::Bool(true)]);
+        assert_eq!(map.values(), vec![LValue::Nil, LValue::Number(4.0)]);
+        assert_eq!(map.get(&LValue::Bool(true), &bracket()), Ok(LValue::Number(4.0)));
+
+        match map.get(&LValue::Nil, &bracket()) {
+            Err(RunTimeError::Error(error)) => assert_eq!(error.message, "Key nil not found in map"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
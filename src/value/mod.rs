@@ -2,19 +2,165 @@ pub mod operation;
 pub mod condition;
 pub mod function;
 pub mod class;
+pub mod native;
+pub mod list;
+pub mod map;
+pub mod exception;
 
-use std::{cell::RefCell, rc::Rc};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use crate::runner::error::{CommonError, RunTimeError};
+use crate::scanner::token::Token;
 use function::Function;
 use class::{Class, ClassInstance};
+use native::NativeFunction;
+use list::List;
+use map::Map;
+use exception::Exception;
+use crate::vm::object::Object;
 
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub enum LValue {
     Number(f64),
     String(String),
     Bool(bool),
     Nil,
     Function(Rc<Function>),
+    NativeFunction(Rc<NativeFunction>),
     Class(Rc<Class>),
     ClassInstance(Rc<ClassInstance>),
-}
\ No newline at end of file
+    List(Rc<List>),
+    Map(Rc<Map>),
+    Exception(Rc<Exception>),
+    // Values of the virtual machine
+    Object(Object),
+}
+
+thread_local! {
+    // Lists and maps being formatted, innermost last
+    static FORMATTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Format a list or map with `format`, or write `repeated` when the
+/// collection is already being formatted, as one containing itself is
+pub(crate) fn format_once(
+    collection: *const (),
+    f: &mut fmt::Formatter<'_>,
+    repeated: &str,
+    format: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    let entered = FORMATTING.with(|formatting| {
+        let mut formatting = formatting.borrow_mut();
+
+        if formatting.contains(&collection) {
+            return false;
+        }
+
+        formatting.push(collection);
+        true
+    });
+
+    if !entered {
+        return f.write_str(repeated);
+    }
+
+    let result = format(f);
+    FORMATTING.with(|formatting| formatting.borrow_mut().pop());
+    result
+}
+
+// Same output as a derived implementation, objects of the virtual machine
+// print like their counterparts of the tree-walking interpreter
+impl fmt::Debug for LValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LValue::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            LValue::String(s) => f.debug_tuple("String").field(s).finish(),
+            LValue::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            LValue::Nil => write!(f, "Nil"),
+            LValue::Function(function) => f.debug_tuple("Function").field(function).finish(),
+            LValue::NativeFunction(function) => f.debug_tuple("NativeFunction").field(function).finish(),
+            LValue::Class(class) => f.debug_tuple("Class").field(class).finish(),
+            LValue::ClassInstance(instance) => f.debug_tuple("ClassInstance").field(instance).finish(),
+            LValue::List(list) => f.debug_tuple("List").field(list).finish(),
+            LValue::Map(map) => f.debug_tuple("Map").field(map).finish(),
+            LValue::Exception(exception) => f.debug_tuple("Exception").field(exception).finish(),
+            LValue::Object(object) => object.fmt(f),
+        }
+    }
+}
+
+// Output of `print`
+impl fmt::Display for LValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LValue::Number(n) => write!(f, "{}", n),
+            LValue::String(s) => write!(f, "{}", s),
+            LValue::Bool(b) => write!(f, "{}", b),
+            LValue::Nil => write!(f, "nil"),
+            LValue::Function(function) => write!(f, "{}", function),
+            LValue::NativeFunction(function) => write!(f, "{}", function),
+            LValue::Class(class) => write!(f, "{}", class),
+            LValue::ClassInstance(instance) => write!(f, "{}", instance),
+            LValue::List(list) => write!(f, "{}", list),
+            LValue::Map(map) => write!(f, "{}", map),
+            LValue::Exception(exception) => write!(f, "{}", exception),
+            LValue::Object(object) => write!(f, "{}", object),
+        }
+    }
+}
+
+impl LValue {
+    /// Displayed value with strings quoted, as shown inside lists and maps
+    /// and by the prompt
+    pub fn repr(&self) -> String {
+        match self {
+            LValue::String(s) => format!("{:?}", s),
+            value => value.to_string(),
+        }
+    }
+
+    /// Name of the type of the value, as used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LValue::Number(_) => "number",
+            LValue::String(_) => "string",
+            LValue::Bool(_) => "bool",
+            LValue::Nil => "nil",
+            LValue::Function(_) | LValue::NativeFunction(_) => "function",
+            LValue::Class(_) => "class",
+            LValue::ClassInstance(_) => "class instance",
+            LValue::List(_) => "list",
+            LValue::Map(_) => "map",
+            LValue::Exception(_) => "exception",
+            LValue::Object(object) => object.kind(),
+        }
+    }
+
+    /// Read `self[index]`, `bracket` is the token errors point at
+    pub fn get_index(&self, index: &LValue, bracket: &Token) -> Result<LValue, RunTimeError> {
+        match self {
+            LValue::List(list) => list.get(index, bracket),
+            LValue::Map(map) => map.get(index, bracket),
+            _ => Err(LValue::not_indexable(bracket)),
+        }
+    }
+
+    /// Write `self[index] = value`, `bracket` is the token errors point at
+    pub fn set_index(&self, index: &LValue, bracket: &Token, value: LValue) -> Result<(), RunTimeError> {
+        match self {
+            LValue::List(list) => list.set(index, bracket, value),
+            LValue::Map(map) => map.set(index, bracket, value),
+            _ => Err(LValue::not_indexable(bracket)),
+        }
+    }
+
+    fn not_indexable(bracket: &Token) -> RunTimeError {
+        RunTimeError::Error(CommonError {
+            token: Some(bracket.clone()),
+            message: String::from("Only lists and maps can be indexed."),
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fmt, rc::Rc};
+use crate::{
+    interpreter::Interpreter,
+    runner::error::RunTimeError,
+    scanner::token::Token,
+};
+use super::{function::Callable, LValue};
+
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<LValue>) -> Result<LValue, RunTimeError>;
+
+/// Function implemented by the host, such as `clock`
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    pub function: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: usize, function: F) -> NativeFunction
+    where
+        F: Fn(&mut Interpreter, Vec<LValue>) -> Result<LValue, RunTimeError> + 'static,
+    {
+        NativeFunction {
+            name: String::from(name),
+            arity,
+            function: Rc::new(function),
+        }
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// Natives are identified by their name, closures can't be compared
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Rc::ptr_eq(&self.function, &other.function)
+    }
+}
+
+// Just for avoiding complie check
+impl PartialOrd for NativeFunction {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.name.partial_cmp(&other.name)
+    }
+}
+
+impl Callable for NativeFunction {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        token: &Token,
+        arguments: Vec<LValue>,
+    ) -> Result<LValue, RunTimeError> {
+        // Natives have no source, their errors point at the call
+        (self.function)(interpreter, arguments).map_err(|error| error.or_token(token))
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+}
@@ -8,7 +8,7 @@ impl ops::Neg for LValue {
     fn neg(self) -> Self::Output {
         match self {
             LValue::Number(n) => Ok(LValue::Number(-n)),
-            _ => Err(format!("Invalid negative operation for type {:?}", self)),
+            _ => Err(format!("Invalid negative operation for type {}", self.kind())),
         }
     }
 }
@@ -40,8 +40,13 @@ impl ops::Add<LValue> for LValue {
                     LValue::Nil => Ok(LValue::Number(l)),
                     LValue::Bool(r) => Ok(LValue::Number(l + f64::from(r))),
                     LValue::Function(_) => Err(String::from("Operation add not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation add not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation add not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation add not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation add not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation add not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation add not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation add not supported for {}", object.kind())),
                 }
             },
             LValue::String(l) => {
@@ -51,8 +56,13 @@ impl ops::Add<LValue> for LValue {
                     LValue::Nil => Ok(LValue::String(l + "nil")),
                     LValue::Bool(r) => Ok(LValue::String(l + r.to_string().as_str())),
                     LValue::Function(_) => Err(String::from("Operation add not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation add not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation add not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation add not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation add not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation add not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation add not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation add not supported for {}", object.kind())),
                 }
             },
             LValue::Bool(l) => {
@@ -62,8 +72,13 @@ impl ops::Add<LValue> for LValue {
                     LValue::Nil => Ok(LValue::Number(f64::from(l))),
                     LValue::Bool(r) => Ok(LValue::Number(f64::from(l) + f64::from(r))),
                     LValue::Function(_) => Err(String::from("Operation add not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation add not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation add not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation add not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation add not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation add not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation add not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation add not supported for {}", object.kind())),
                 }
             },
             LValue::Nil => {
@@ -73,13 +88,23 @@ impl ops::Add<LValue> for LValue {
                     LValue::Nil => Ok(LValue::Number(0.0)),
                     LValue::Bool(r) => Ok(LValue::Number(f64::from(r))),
                     LValue::Function(_) => Err(String::from("Operation add not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation add not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation add not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation add not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation add not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation add not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation add not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation add not supported for {}", object.kind())),
                 }
             },
             LValue::Function(_) => Err(String::from("Operation add not supported for function")),
+            LValue::NativeFunction(_) => Err(String::from("Operation add not supported for function")),
             LValue::Class(_) => Err(String::from("Operation add not supported for class")),
             LValue::ClassInstance(_) => Err(String::from("Operation add not supported for class instance")),
+            LValue::List(_) => Err(String::from("Operation add not supported for list")),
+            LValue::Map(_) => Err(String::from("Operation add not supported for map")),
+            LValue::Exception(_) => Err(String::from("Operation add not supported for exception")),
+            LValue::Object(object) => Err(format!("Operation add not supported for {}", object.kind())),
         }
     }
 }
@@ -97,13 +122,16 @@ impl ops::Sub<LValue> for LValue {
                     LValue::Nil => Ok(LValue::Number(l)),
                     LValue::Bool(r) => Ok(LValue::Number(l - f64::from(r))),
                     LValue::Function(_) => Err(String::from("Operation sub not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation sub not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation sub not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation sub not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation sub not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation sub not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation sub not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation sub not supported for {}", object.kind())),
                 }
             },
-            LValue::String(_) => {
-                panic!("String is not support for subtraction")
-            },
+            LValue::String(_) => Err(String::from("Operation sub not supported for string")),
             LValue::Bool(l) => {
                 match rhs {
                     LValue::Number(r) => Ok(LValue::Number(f64::from(l) - r)),
@@ -111,8 +139,13 @@ impl ops::Sub<LValue> for LValue {
                     LValue::Nil => Ok(LValue::Number(f64::from(l))),
                     LValue::Bool(r) => Ok(LValue::Number(f64::from(l) - f64::from(r))),
                     LValue::Function(_) => Err(String::from("Operation sub not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation sub not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation sub not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation sub not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation sub not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation sub not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation sub not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation sub not supported for {}", object.kind())),
                 }
             },
             LValue::Nil => {
@@ -122,13 +155,23 @@ impl ops::Sub<LValue> for LValue {
                     LValue::Nil => Ok(LValue::Number(0.0)),
                     LValue::Bool(r) => Ok(LValue::Number(0.0 - f64::from(r))),
                     LValue::Function(_) => Err(String::from("Operation sub not supported for function")),
+                    LValue::NativeFunction(_) => Err(String::from("Operation sub not supported for function")),
                     LValue::Class(_) => Err(String::from("Operation sub not supported for class")),
                     LValue::ClassInstance(_) => Err(String::from("Operation sub not supported for class instance")),
+                    LValue::List(_) => Err(String::from("Operation sub not supported for list")),
+                    LValue::Map(_) => Err(String::from("Operation sub not supported for map")),
+                    LValue::Exception(_) => Err(String::from("Operation sub not supported for exception")),
+                    LValue::Object(object) => Err(format!("Operation sub not supported for {}", object.kind())),
                 }
             },
             LValue::Function(_) => Err(String::from("Operation sub not supported for function")),
+            LValue::NativeFunction(_) => Err(String::from("Operation sub not supported for function")),
             LValue::Class(_) => Err(String::from("Operation sub not supported for class")),
             LValue::ClassInstance(_) => Err(String::from("Operation sub not supported for class instance")),
+            LValue::List(_) => Err(String::from("Operation sub not supported for list")),
+            LValue::Map(_) => Err(String::from("Operation sub not supported for map")),
+            LValue::Exception(_) => Err(String::from("Operation sub not supported for exception")),
+            LValue::Object(object) => Err(format!("Operation sub not supported for {}", object.kind())),
         }
     }
 }
@@ -165,4 +208,117 @@ impl ops::Div<LValue> for LValue {
             _ => Err(String::from("Operation divide only supports for Number")),
         }
     }
-}
\ No newline at end of file
+}
+
+// Binary Operation modulo: %, floored so the result takes the sign of the
+// divisor like floor division does
+impl ops::Rem<LValue> for LValue {
+    type Output = Result<LValue, String>;
+
+    fn rem(self, rhs: LValue) -> Self::Output {
+        match (self, rhs) {
+            (LValue::Number(l), LValue::Number(r)) => {
+                let remainder = l % r;
+
+                if remainder != 0.0 && (remainder < 0.0) != (r < 0.0) {
+                    Ok(LValue::Number(remainder + r))
+                } else {
+                    Ok(LValue::Number(remainder))
+                }
+            },
+            _ => Err(String::from("Operation modulo only supports for Number")),
+        }
+    }
+}
+
+// Binary Operation bitwise and: &
+impl ops::BitAnd<LValue> for LValue {
+    type Output = Result<LValue, String>;
+
+    fn bitand(self, rhs: LValue) -> Self::Output {
+        let (l, r) = (self.integer("bitwise and")?, rhs.integer("bitwise and")?);
+        Ok(LValue::Number((l & r) as f64))
+    }
+}
+
+// Binary Operation bitwise or: |
+impl ops::BitOr<LValue> for LValue {
+    type Output = Result<LValue, String>;
+
+    fn bitor(self, rhs: LValue) -> Self::Output {
+        let (l, r) = (self.integer("bitwise or")?, rhs.integer("bitwise or")?);
+        Ok(LValue::Number((l | r) as f64))
+    }
+}
+
+// Binary Operation bitwise xor: ^
+impl ops::BitXor<LValue> for LValue {
+    type Output = Result<LValue, String>;
+
+    fn bitxor(self, rhs: LValue) -> Self::Output {
+        let (l, r) = (self.integer("bitwise xor")?, rhs.integer("bitwise xor")?);
+        Ok(LValue::Number((l ^ r) as f64))
+    }
+}
+
+// Binary Operation shift left: <<, bits shifted past the 64th are dropped
+impl ops::Shl<LValue> for LValue {
+    type Output = Result<LValue, String>;
+
+    fn shl(self, rhs: LValue) -> Self::Output {
+        let l = self.integer("shift left")?;
+        let r = rhs.shift_amount("shift left")?;
+        Ok(LValue::Number((l << r) as f64))
+    }
+}
+
+// Binary Operation shift right: >>, keeps the sign
+impl ops::Shr<LValue> for LValue {
+    type Output = Result<LValue, String>;
+
+    fn shr(self, rhs: LValue) -> Self::Output {
+        let l = self.integer("shift right")?;
+        let r = rhs.shift_amount("shift right")?;
+        Ok(LValue::Number((l >> r) as f64))
+    }
+}
+
+impl LValue {
+    // Binary Operation power: **
+    pub fn pow(self, rhs: LValue) -> Result<LValue, String> {
+        match (self, rhs) {
+            (LValue::Number(l), LValue::Number(r)) => Ok(LValue::Number(l.powf(r))),
+            _ => Err(String::from("Operation power only supports for Number")),
+        }
+    }
+
+    // Binary Operation floor divide: ~/
+    pub fn floor_div(self, rhs: LValue) -> Result<LValue, String> {
+        match (self, rhs) {
+            (LValue::Number(l), LValue::Number(r)) => Ok(LValue::Number((l / r).floor())),
+            _ => Err(String::from("Operation floor divide only supports for Number")),
+        }
+    }
+
+    // Unary Operation bitwise not: ~
+    pub fn bit_not(self) -> Result<LValue, String> {
+        Ok(LValue::Number(!self.integer("bitwise not")? as f64))
+    }
+
+    // Operand of a bitwise operation, a number without fraction that fits
+    // in 64 bits
+    fn integer(&self, operation: &str) -> Result<i64, String> {
+        match self {
+            LValue::Number(n) if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 => Ok(*n as i64),
+            LValue::Number(n) => Err(format!("Operation {} only supports for integral Number, got {}", operation, n)),
+            _ => Err(format!("Operation {} only supports for Number", operation)),
+        }
+    }
+
+    fn shift_amount(&self, operation: &str) -> Result<u32, String> {
+        match self.integer(operation)? {
+            amount @ 0..=63 => Ok(amount as u32),
+            amount => Err(format!("Operation {} only supports shifting by 0 to 63 bits, got {}", operation, amount)),
+        }
+    }
+}
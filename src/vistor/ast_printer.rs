@@ -1,6 +1,9 @@
-use std::fmt::Arguments;
-
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::environment::Environment;
 use crate::grammer::expression::{Expr, ExprAccept, ExprLiteral, ExprVistor};
+use crate::grammer::statement::{Stmt, StmtAccept, StmtVistor};
+use crate::scanner::token::Token;
 
 pub struct AstPrinter;
 
@@ -10,22 +13,41 @@ impl AstPrinter {
     }
 
     pub fn print(&mut self, expr: Expr) -> String {
-        let output = expr.accept(self);
-        println!("{}", output);
+        expr.accept(self)
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    fn lexeme(token: &Token) -> String {
+        String::from_utf8_lossy(&token.lexeme).to_string()
+    }
+
+    fn parenthesize_stmt(&mut self, name: &str, parts: Vec<String>) -> String {
+        let mut output = String::from("(");
+        output.push_str(name);
+
+        for part in parts {
+            output.push(' ');
+            output.push_str(part.as_str());
+        }
+
+        output.push(')');
         output
     }
 
     fn parenthesize(&mut self, name: &str, exprs: &Vec<&Expr>) -> String {
         let mut output = String::from("");
-        output.push_str("(");
+        output.push('(');
         output.push_str(name);
 
         for expr in exprs {
-            output.push_str(" ");
+            output.push(' ');
             output.push_str(expr.accept(self).as_str());
         }
 
-        output.push_str(")");
+        output.push(')');
 
         output.to_string()
     }
@@ -61,10 +83,10 @@ impl ExprVistor<String> for AstPrinter {
                     ExprLiteral::Number(n) => n.to_string(),
                 }
             },
-            Expr::Variable(expr) => {
+            Expr::Variable(expr, _) => {
                 format!("idt {:?}", expr.literal)
             },
-            Expr::Assign(token, expr) => {
+            Expr::Assign(token, expr, _) => {
                 self.parenthesize(
                     format!("{:?} = ", String::from_utf8(token.lexeme.to_vec())).as_str(),
                     &vec![expr],
@@ -76,7 +98,7 @@ impl ExprVistor<String> for AstPrinter {
                     &vec![l, r],
                 )   
             },
-            Expr::Call(callee, token, arguments) => {
+            Expr::Call(callee, _token, arguments) => {
                 self.parenthesize(
                     format!("func {:#?} ", callee).as_str(),
                     &(arguments.iter().collect()),
@@ -84,36 +106,158 @@ impl ExprVistor<String> for AstPrinter {
             },
             Expr::New(_, call_expr) => {
                 self.parenthesize(
-                    format!("new class").as_str(),
+                    "new class",
                     &vec![call_expr],
                 )
             },
-            Expr::Get(object, property) => {
+            Expr::Get(object, _property) => {
                 self.parenthesize(
-                    format!("get").as_str(),
+                    "get",
                     &vec![object],
                 )
             },
-            Expr::Set(object, property, value) => {
+            Expr::Set(object, _property, value) => {
                 self.parenthesize(
-                    format!("set").as_str(),
+                    "set",
                     &vec![object, value],
                 )
             },
-            Expr::This(_) => {
-                format!("this ")
+            Expr::This(_, _) => {
+                "this ".to_string()
             },
-            Expr::Super(_, method) => {
+            Expr::Super(_, method, _) => {
                 self.parenthesize(
-                    format!("super").as_str(),
+                    "super",
                     &vec![method] 
                 )
             },
+            Expr::Interpolation(_, parts) => {
+                self.parenthesize(
+                    "interpolation",
+                    &(parts.iter().collect()),
+                )
+            },
+            Expr::List(_, elements) => {
+                self.parenthesize(
+                    "list",
+                    &(elements.iter().collect()),
+                )
+            },
+            Expr::Map(_, pairs) => {
+                let entries: Vec<&Expr> = pairs
+                    .iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect();
+                self.parenthesize(
+                    "map",
+                    &entries,
+                )
+            },
+            Expr::Index(object, _, index) => {
+                self.parenthesize(
+                    "index",
+                    &vec![object, index],
+                )
+            },
+            Expr::SetIndex(object, _, index, value) => {
+                self.parenthesize(
+                    "set index",
+                    &vec![object, index, value],
+                )
+            },
+            Expr::Lambda(_, params, _) => {
+                let names: Vec<String> = params
+                    .iter()
+                    .map(|param| String::from_utf8_lossy(&param.lexeme).into_owned())
+                    .collect();
+                format!("(lambda {})", names.join(" "))
+            },
         }
     }
 }
 
 
+impl StmtVistor<String> for AstPrinter {
+    fn visit(&mut self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expr(expr) => {
+                let expr = expr.accept(self);
+                self.parenthesize_stmt("expr", vec![expr])
+            },
+            Stmt::Print(expr) => {
+                let expr = expr.accept(self);
+                self.parenthesize_stmt("print", vec![expr])
+            },
+            Stmt::Var(name, initializer) => {
+                let mut parts = vec![AstPrinter::lexeme(name)];
+                if let Some(initializer) = initializer {
+                    parts.push(initializer.accept(self));
+                }
+                self.parenthesize_stmt("var", parts)
+            },
+            Stmt::Block(statements) => {
+                let parts = statements.iter().map(|stmt| stmt.accept(self)).collect();
+                self.parenthesize_stmt("block", parts)
+            },
+            Stmt::If(condition, then_branch, else_branch) => {
+                let mut parts = vec![condition.accept(self), then_branch.accept(self)];
+                if let Some(else_branch) = else_branch.as_ref() {
+                    parts.push(else_branch.accept(self));
+                }
+                self.parenthesize_stmt("if", parts)
+            },
+            Stmt::While(condition, body, increment) => {
+                let mut parts = vec![condition.accept(self), body.accept(self)];
+                if let Some(increment) = increment {
+                    parts.push(increment.accept(self));
+                }
+                self.parenthesize_stmt("while", parts)
+            },
+            Stmt::Function(name, params, body) => {
+                let params: Vec<String> = params.iter().map(AstPrinter::lexeme).collect();
+                let parts = vec![
+                    AstPrinter::lexeme(name),
+                    format!("({})", params.join(" ")),
+                    body.accept(self),
+                ];
+                self.parenthesize_stmt("fun", parts)
+            },
+            Stmt::Class(name, superclass, methods) => {
+                let mut parts = vec![AstPrinter::lexeme(name)];
+                if let Some(superclass) = superclass {
+                    parts.push(format!("(extend {})", superclass.accept(self)));
+                }
+                parts.extend(methods.iter().map(|method| method.accept(self)));
+                self.parenthesize_stmt("class", parts)
+            },
+            Stmt::Return(_, value) => {
+                let parts = value.iter().map(|value| value.accept(self)).collect();
+                self.parenthesize_stmt("return", parts)
+            },
+            Stmt::Throw(_, value) => {
+                let parts = vec![value.accept(self)];
+                self.parenthesize_stmt("throw", parts)
+            },
+            Stmt::Try(body, catch, finally) => {
+                let mut parts = vec![body.accept(self)];
+                if let Some((name, handler)) = catch {
+                    parts.push(format!("(catch {} {})", AstPrinter::lexeme(name), handler.accept(self)));
+                }
+                if let Some(finally) = finally {
+                    parts.push(format!("(finally {})", finally.accept(self)));
+                }
+                self.parenthesize_stmt("try", parts)
+            },
+            Stmt::Break(_) => self.parenthesize_stmt("break", Vec::new()),
+            Stmt::Continue(_) => self.parenthesize_stmt("continue", Vec::new()),
+        }
+    }
+
+    fn visit_env(&mut self, stmt: &Stmt, _env: Rc<RefCell<Environment>>) -> String {
+        stmt.accept(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,45 +1,106 @@
+use std::cell::Cell;
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::environment::Environment;
 use crate::grammer::expression::*;
 use crate::runner::error::{CommonError, RunTimeError};
 use crate::scanner::token::{Token, TokenType};
-use crate::value::class::ClassInstance;
 use crate::value::condition::IsTruthy;
-use crate::value::function::Callable;
+use crate::value::function::{Callable, Function};
+use crate::value::list::List;
+use crate::value::map::{Map, MapKey};
 use crate::value::LValue;
 use crate::interpreter::Interpreter;
 
 impl Interpreter {
+    #[cfg(test)]
     pub fn interpret_expr_debug(&mut self, expr: &Expr) -> Result<LValue, RunTimeError> {
         self.evaluate(expr)
     }
 
     pub fn evaluate(&mut self, expr: &Expr) -> Result<LValue, RunTimeError>{
+        self.budget.step()?;
         expr.accept(self)
     }
 
-    fn result_expr_helper(operation_result: Result<LValue, String>, token: &Token) -> Result<LValue, RunTimeError> {
+    pub fn result_expr_helper(operation_result: Result<LValue, String>, token: &Token) -> Result<LValue, RunTimeError> {
         match operation_result {
             Ok(l_value) => Ok(l_value),
             Err(message) => Err(RunTimeError::Error(
                 CommonError {
-                    message: message,
+                    message,
                     token: Some(token.clone()),
                 }
             ))
         }
     }
 
-    fn lookup_variable(&mut self, name: &Token) -> Result<LValue, RunTimeError> {
-        let distance_option: Option<&usize> = self.locals.get(&name);
+    fn lookup_variable(&mut self, name: &Token, resolution: &Cell<Resolution>) -> Result<LValue, RunTimeError> {
+        match self.resolve_variable(name, resolution) {
+            Resolution::Local { depth, slot } => self.environment.borrow().get_at(depth, slot, name),
+            Resolution::Global(symbol) => self.globals.get(symbol, name),
+            Resolution::Unresolved => Err(Environment::undefined(name)),
+        }
+    }
+
+    fn assign_variable(&mut self, name: &Token, resolution: &Cell<Resolution>, value: LValue) -> Result<(), RunTimeError> {
+        match self.resolve_variable(name, resolution) {
+            Resolution::Local { depth, slot } => self.environment.borrow_mut().assign_at(depth, slot, name, value),
+            Resolution::Global(symbol) => self.globals.assign(symbol, name, value),
+            Resolution::Unresolved => Err(Environment::undefined(name)),
+        }
+    }
+
+    // Kept out of `visit`, whose frame is on the stack for every nested
+    // expression being evaluated
+    fn interpolate(&mut self, parts: &[Expr]) -> Result<LValue, RunTimeError> {
+        let mut text = String::new();
+
+        for part in parts {
+            text.push_str(&self.evaluate(part)?.to_string());
+        }
+
+        Ok(LValue::String(text))
+    }
 
-        if let Some(distance) = distance_option {
-            return self.environment.borrow().get_at(*distance, name);
-        } else {
-            return self.globals.borrow().get(name)
+    // Kept out of `visit` as well, one arm per operator adds up
+    fn binary(left: LValue, token: &Token, right: LValue) -> Result<LValue, RunTimeError> {
+        match token.typee {
+            TokenType::Minus => Interpreter::result_expr_helper(left - right, token),
+            TokenType::Plus => Interpreter::result_expr_helper(left + right, token),
+            TokenType::Slash => Interpreter::result_expr_helper(left / right, token),
+            TokenType::Star => Interpreter::result_expr_helper(left * right, token),
+            TokenType::Percent => Interpreter::result_expr_helper(left % right, token),
+            TokenType::TildeSlash => Interpreter::result_expr_helper(left.floor_div(right), token),
+            TokenType::StarStar => Interpreter::result_expr_helper(left.pow(right), token),
+            TokenType::Ampersand => Interpreter::result_expr_helper(left & right, token),
+            TokenType::Pipe => Interpreter::result_expr_helper(left | right, token),
+            TokenType::Caret => Interpreter::result_expr_helper(left ^ right, token),
+            TokenType::LessLess => Interpreter::result_expr_helper(left << right, token),
+            TokenType::GreaterGreater => Interpreter::result_expr_helper(left >> right, token),
+            TokenType::Greater => Ok(LValue::Bool(left > right)),
+            TokenType::GreaterEqual => Ok(LValue::Bool(left >= right)),
+            TokenType::Less => Ok(LValue::Bool(left < right)),
+            TokenType::LessEqual => Ok(LValue::Bool(left <= right)),
+            TokenType::BangEqual => Ok(LValue::Bool(left != right)),
+            TokenType::EqualEqual => Ok(LValue::Bool(left == right)),
+            _ => Err(RunTimeError::Error(CommonError {
+                token: Some(token.clone()),
+                message: String::from("Wrong token type evaluating for binary expression"),
+            })),
         }
     }
+
+    // Code that skipped the resolver, like a lone expression, can only
+    // refer to globals
+    fn resolve_variable(&mut self, name: &Token, resolution: &Cell<Resolution>) -> Resolution {
+        if resolution.get() == Resolution::Unresolved {
+            resolution.set(Resolution::Global(self.globals.intern_token(name)));
+        }
+
+        resolution.get()
+    }
 }
 
 impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
@@ -63,6 +124,7 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                 match token.typee {
                     TokenType::Minus => Interpreter::result_expr_helper(-right, token),
                     TokenType::Bang => Interpreter::result_expr_helper(!right, token),
+                    TokenType::Tilde => Interpreter::result_expr_helper(right.bit_not(), token),
                     _ => Err(RunTimeError::Error(
                         CommonError {
                             token: Some(token.clone()),
@@ -75,27 +137,12 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                 let left = self.evaluate(l)?;
                 let right = self.evaluate(r)?;
 
-                match token.typee {
-                    TokenType::Minus => Interpreter::result_expr_helper(left - right, token),
-                    TokenType::Plus => Interpreter::result_expr_helper(left + right, token),
-                    TokenType::Slash => Interpreter::result_expr_helper(left / right, token),
-                    TokenType::Star => Interpreter::result_expr_helper(left * right, token),
-                    TokenType::Greater => Ok(LValue::Bool(left > right)),
-                    TokenType::GreaterEqual => Ok(LValue::Bool(left >= right)),
-                    TokenType::Less => Ok(LValue::Bool(left < right)),
-                    TokenType::LessEqual => Ok(LValue::Bool(left <= right)),
-                    TokenType::BangEqual => Ok(LValue::Bool(left != right)),
-                    TokenType::EqualEqual => Ok(LValue::Bool(left == right)),
-                    _ => Err(RunTimeError::Error(CommonError {
-                        token: Some(token.clone()),
-                        message: String::from("Wrong token type evaluating for binary expression"),
-                    })),
-                }
+                Interpreter::binary(left, token, right)
             },
-            Expr::Variable(token) => {
+            Expr::Variable(token, resolution) => {
                 match token.typee {
                     TokenType::Identifier => {
-                        self.lookup_variable(token)
+                        self.lookup_variable(token, resolution)
                     },
                     _ => Err(RunTimeError::Error(CommonError {
                         token: Some(token.clone()),
@@ -103,22 +150,12 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                     })),
                 }
             },
-            Expr::Assign(token, expr) => {
+            Expr::Assign(token, expr, resolution) => {
                 match token.typee {
                     TokenType::Identifier => {
                         let value = self.evaluate(expr)?;
-                        let distance_option = self.locals.get(token);
-                        
-                        if let Some(distance) = distance_option {
-                            self.environment.borrow_mut().assign_at(*distance, token, value.clone())?;
-                            return Ok(value);
-                        } else {
-                            let assgin_res = self.globals.borrow_mut().assign(token, value.clone());
-                            match assgin_res {
-                                Ok(_) => return Ok(value),
-                                Err(err) => return Err(err),
-                            }
-                        }
+                        self.assign_variable(token, resolution, value.clone())?;
+                        Ok(value)
                     },
                     _ => Err(RunTimeError::Error(CommonError {
                         token: Some(token.clone()),
@@ -129,33 +166,11 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
             Expr::Logical(left, token, right) => {
                 let left_val = self.evaluate(left)?;
 
+                // The operand deciding the result is the result
                 match token.typee {
-                    TokenType::Or => {
-                        if left_val.is_truthy() {
-                            return Ok(LValue::Bool(true));
-                        }
-                        
-                        let right_val = self.evaluate(right)?;
-                        
-                        if right_val.is_truthy() {
-                            return Ok(LValue::Bool(true));
-                        } else {
-                            return Ok(LValue::Bool(false));
-                        }
-                    },
-                    TokenType::And => {
-                        if !left_val.is_truthy() {
-                            return Ok(LValue::Bool(false));
-                        }
-
-                        let right_val = self.evaluate(right)?;
-
-                        if right_val.is_truthy() {
-                            return Ok(LValue::Bool(true));
-                        } else {
-                            return Ok(LValue::Bool(false));
-                        }
-                    },
+                    TokenType::Or if left_val.is_truthy() => Ok(left_val),
+                    TokenType::And if !left_val.is_truthy() => Ok(left_val),
+                    TokenType::Or | TokenType::And => self.evaluate(right),
                     _ => Err(RunTimeError::Error(CommonError {
                         token: Some(token.clone()),
                         message: String::from("Wrong token type evaluating for logical expression"),
@@ -167,17 +182,35 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
 
                 let mut arguments_val: Vec<LValue> = Vec::new();
 
-                // TODO: arity check
                 for argument in arguments {
                     arguments_val.push(self.evaluate(argument)?);
                 }
 
+                let arity = match &callee_val {
+                    LValue::Function(function) => Some(function.arity()),
+                    LValue::NativeFunction(function) => Some(function.arity()),
+                    LValue::Class(class) => Some(class.arity()),
+                    _ => None,
+                };
+
+                if let Some(arity) = arity {
+                    if arity != arguments_val.len() {
+                        return Err(RunTimeError::Error(CommonError {
+                            token: Some(paren.clone()),
+                            message: format!("Expected {} arguments but got {}.", arity, arguments_val.len()),
+                        }));
+                    }
+                }
+
                 match callee_val {
                     LValue::Function(function) => {
-                        return function.call(self, paren, arguments_val);
+                        function.call(self, paren, arguments_val)
+                    },
+                    LValue::NativeFunction(function) => {
+                        function.call(self, paren, arguments_val)
                     },
                     LValue::Class(class) => {
-                        return class.call(self, paren, arguments_val);
+                        class.call(self, paren, arguments_val)
                     },
                     _ => {
                         Err(RunTimeError::Error(CommonError {
@@ -187,17 +220,17 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                     },
                 }
             },
-            Expr::New(token, caller) => {
-                let val = self.evaluate(caller);
-                return val;
+            Expr::New(_token, caller) => {
+                self.evaluate(caller)
             },
             Expr::Get(object, field) => {
                 let object_val = self.evaluate(object)?;
 
                 match object_val {
                     LValue::ClassInstance(instance) => {
-                        return Ok(instance.clone().get(field)?);
+                        instance.clone().get(field)
                     },
+                    LValue::Exception(exception) => exception.get(field),
                     _ => {
                         Err(RunTimeError::Error(CommonError {
                             token: Some(field.clone()),
@@ -213,7 +246,7 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                     LValue::ClassInstance(instance) => {
                         let value = self.evaluate(val)?;
                         instance.set(field, value.clone())?;
-                        return Ok(value);
+                        Ok(value)
                     },
                     _ => {
                         Err(RunTimeError::Error(CommonError {
@@ -223,13 +256,70 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                     }
                 }
             },
-            Expr::This(token) => {
-                Ok(self.lookup_variable(token)?)
+            Expr::This(token, resolution) => {
+                Ok(self.lookup_variable(token, resolution)?)
             },
-            Expr::Super(token, method) => {
-                let distance = self.locals.get(token).unwrap();
-                let super_class = self.environment.borrow_mut().get_at(*distance, token)?;
-                let object = self.environment.borrow_mut().get_at(distance - 1, &Token {
+            Expr::Interpolation(_, parts) => self.interpolate(parts),
+            Expr::List(_, elements) => {
+                let mut values: Vec<LValue> = Vec::with_capacity(elements.len());
+
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+
+                Ok(LValue::List(List::new(values)))
+            },
+            Expr::Map(brace, pairs) => {
+                let mut values: Vec<(LValue, LValue)> = Vec::with_capacity(pairs.len());
+
+                for (key, value) in pairs {
+                    values.push((self.evaluate(key)?, self.evaluate(value)?));
+                }
+
+                // Keys are checked once every entry is evaluated
+                let mut entries: Vec<(MapKey, LValue)> = Vec::with_capacity(values.len());
+
+                for (key, value) in values {
+                    let key = MapKey::from_value(&key).map_err(|message| RunTimeError::Error(CommonError {
+                        token: Some(brace.clone()),
+                        message,
+                    }))?;
+                    entries.push((key, value));
+                }
+
+                Ok(LValue::Map(Map::new(entries)))
+            },
+            Expr::Index(object, bracket, index) => {
+                let object_val = self.evaluate(object)?;
+                let index_val = self.evaluate(index)?;
+                object_val.get_index(&index_val, bracket)
+            },
+            Expr::SetIndex(object, bracket, index, value) => {
+                let object_val = self.evaluate(object)?;
+                let index_val = self.evaluate(index)?;
+                let value = self.evaluate(value)?;
+                object_val.set_index(&index_val, bracket, value.clone())?;
+                Ok(value)
+            },
+            Expr::Lambda(keyword, params, body) => {
+                Ok(LValue::Function(Rc::new(Function {
+                    name: keyword.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: self.environment.clone(),
+                    is_initializer: false,
+                    class: None,
+                })))
+            },
+            Expr::Super(token, method, resolution) => {
+                let depth = match resolution.get() {
+                    Resolution::Local { depth, .. } if depth > 0 => depth,
+                    _ => return Err(Environment::undefined(token)),
+                };
+
+                // `super` and `this` are the only slot of their scopes
+                let super_class = self.environment.borrow().get_at(depth, 0, token)?;
+                let object = self.environment.borrow().get_at(depth - 1, 0, &Token {
                     typee: TokenType::This,
                     line: 0,
                     col: 0,
@@ -238,17 +328,20 @@ impl ExprVistor<Result<LValue, RunTimeError>> for Interpreter {
                 })?;
 
                 if let LValue::Class(super_class_val) = super_class {
-                    if let Expr::Variable(method_token) = method.deref() {
-                        let method_val = super_class_val.find_method(method_token).unwrap();
+                    if let Expr::Variable(method_token, _) = method.deref() {
+                        let method_val = super_class_val.find_method(method_token).ok_or_else(|| RunTimeError::Error(CommonError {
+                            token: Some(method_token.clone()),
+                            message: format!("Undefined property {}", String::from_utf8_lossy(&method_token.lexeme)),
+                        }))?;
                         if let LValue::ClassInstance(class_instance) = object {
                             return Ok(LValue::Function(method_val.bind(class_instance)));
                         }
                     }
                     
                     // Won't go in to this branch
-                    return Ok(LValue::Nil);
+                    Ok(LValue::Nil)
                 } else {
-                    return Err(RunTimeError::Error(CommonError {
+                    Err(RunTimeError::Error(CommonError {
                         token: Some(token.clone()),
                         message: String::from("No super class found"),
                     }))
@@ -285,6 +378,18 @@ mod tests {
             (String::from("\"abc\" + 1"), LValue::String(String::from("abc1"))),
             (String::from("\"abc\" + true"), LValue::String(String::from("abctrue"))),
             (String::from("\"abc\" + false"), LValue::String(String::from("abcfalse"))),
+            (String::from("nil or \"default\""), LValue::String(String::from("default"))),
+            (String::from("\"value\" or \"default\""), LValue::String(String::from("value"))),
+            (String::from("1 and 2"), LValue::Number(2.0)),
+            (String::from("nil and 2"), LValue::Nil),
+            (String::from("false or nil"), LValue::Nil),
+            (String::from("1 or 2 and nil"), LValue::Number(1.0)),
+            (String::from("-7 % 3"), LValue::Number(2.0)),
+            (String::from("-7 ~/ 2"), LValue::Number(-4.0)),
+            (String::from("-2 ** 2"), LValue::Number(-4.0)),
+            (String::from("2 ** 3 ** 2"), LValue::Number(512.0)),
+            (String::from("~5 & 0xff ^ 1 << 2 | 1"), LValue::Number(255.0)),
+            (String::from("-16 >> 2"), LValue::Number(-4.0)),
         ];
 
         for (source, expected) in source_expected {
@@ -340,6 +445,45 @@ mod tests {
                     message: String::from("Invalid operation subtract between number and string"),
                 }),
             ),
+            (
+                String::from("3 | 0.5"),
+                RunTimeError::Error(CommonError {
+                    token: Some(Token {
+                        typee: TokenType::Pipe,
+                        lexeme: String::from("|").into(),
+                        literal: None,
+                        line: 1,
+                        col: 3,
+                    }),
+                    message: String::from("Operation bitwise or only supports for integral Number, got 0.5"),
+                }),
+            ),
+            (
+                String::from("1 >> -1"),
+                RunTimeError::Error(CommonError {
+                    token: Some(Token {
+                        typee: TokenType::GreaterGreater,
+                        lexeme: String::from(">>").into(),
+                        literal: None,
+                        line: 1,
+                        col: 3,
+                    }),
+                    message: String::from("Operation shift right only supports shifting by 0 to 63 bits, got -1"),
+                }),
+            ),
+            (
+                String::from("\"a\" % 2"),
+                RunTimeError::Error(CommonError {
+                    token: Some(Token {
+                        typee: TokenType::Percent,
+                        lexeme: String::from("%").into(),
+                        literal: None,
+                        line: 1,
+                        col: 5,
+                    }),
+                    message: String::from("Operation modulo only supports for Number"),
+                }),
+            ),
         ];
 
         for (source, expected) in source_expected {
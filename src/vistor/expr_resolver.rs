@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use crate::resolver::{*};
 use crate::grammer::expression::{*};
 use crate::scanner::token::Token;
@@ -8,28 +9,26 @@ impl Resolver<'_> {
         expr.accept(self)
     }
 
-    fn resolve_local(&mut self, name: &Token) -> Result<(), ResolveError> {
-        let scope_size = self.scope_size();
+    fn resolve_local(&mut self, name: &Token, resolution: &Cell<Resolution>) -> Result<(), ResolveError> {
         let name_string = String::from_utf8(name.lexeme.to_vec()).unwrap();
 
-        for index in (0..scope_size).rev() {
-            if self.scopes.get(index).unwrap().contains_key(&name_string) {
-                self.interpreter.resolve(name, scope_size - index - 1);
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(binding) = scope.get(&name_string) {
+                resolution.set(Resolution::Local { depth, slot: binding.slot });
                 return Ok(());
             }
         }
 
-        Err(ResolveError::CommonError {
-            token: name.clone(),
-            message: String::from("Can't read local variable in its own initializer."),
-        })
+        // Not found in any local scope, leave it to the globals at runtime
+        resolution.set(Resolution::Global(self.interpreter.globals.intern(&name_string)));
+        Ok(())
     }
 }
 
 impl ExprVistor<Result<(), ResolveError>> for Resolver<'_> {
     fn visit(&mut self, expr: &Expr) -> Result<(), ResolveError> {
         match expr {
-            Expr::Variable(name) => {
+            Expr::Variable(name, resolution) => {
                 let name_string = String::from_utf8(name.lexeme.to_vec()).unwrap();
                 let current_scope_val = self.get_current_val(name_string);
 
@@ -42,12 +41,12 @@ impl ExprVistor<Result<(), ResolveError>> for Resolver<'_> {
                     }
                 }
 
-                self.resolve_local(name)?;
+                self.resolve_local(name, resolution)?;
                 Ok(())
             },
-            Expr::Assign(token, val) => {
+            Expr::Assign(token, val, resolution) => {
                 self.resolve_expr(val)?;
-                self.resolve_local(token)?;
+                self.resolve_local(token, resolution)?;
 
                 Ok(())
             },
@@ -74,16 +73,16 @@ impl ExprVistor<Result<(), ResolveError>> for Resolver<'_> {
                 Ok(())
             },
             // TODO: Add rules which is: new only for class not funciton
-            Expr::Get(object, property) => {
+            Expr::Get(object, _property) => {
                 self.resolve_expr(object)?;
                 Ok(())
             },
-            Expr::Set(object, property, val) => {
+            Expr::Set(object, _property, val) => {
                 self.resolve_expr(object)?;
                 self.resolve_expr(val)?;
                 Ok(())
             },
-            Expr::This(token) => {
+            Expr::This(token, resolution) => {
                 if self.current_class == ClassStatus::None {
                     return Err(ResolveError::CommonError {
                         token: token.clone(),
@@ -91,14 +90,56 @@ impl ExprVistor<Result<(), ResolveError>> for Resolver<'_> {
                     });
                 }
 
-                self.resolve_local(token)?;
+                self.resolve_local(token, resolution)?;
                 Ok(())
             },
-            Expr::Super(token, method) => {
-                self.resolve_local(token)?;
+            Expr::Super(token, _method, resolution) => {
+                self.resolve_local(token, resolution)?;
                 Ok(())
             },
-            _ => Ok(()),
+            Expr::Logical(l, _, r) => {
+                self.resolve_expr(l)?;
+                self.resolve_expr(r)?;
+                Ok(())
+            },
+            Expr::New(_, call) => {
+                self.resolve_expr(call)?;
+                Ok(())
+            },
+            Expr::Lambda(_, params, body) => {
+                self.resolve_function(params, body, FunctionStatus::Function)
+            },
+            Expr::Interpolation(_, parts) => {
+                for part in parts {
+                    self.resolve_expr(part)?;
+                }
+                Ok(())
+            },
+            Expr::List(_, elements) => {
+                for element in elements {
+                    self.resolve_expr(element)?;
+                }
+                Ok(())
+            },
+            Expr::Map(_, pairs) => {
+                for (key, value) in pairs {
+                    self.resolve_expr(key)?;
+                    self.resolve_expr(value)?;
+                }
+                Ok(())
+            },
+            Expr::Index(object, _, index) => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)?;
+                Ok(())
+            },
+            Expr::SetIndex(object, _, index, value) => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)?;
+                self.resolve_expr(value)?;
+                Ok(())
+            },
+            Expr::Literal(_) => Ok(()),
         }
     }
 }
\ No newline at end of file
@@ -1,6 +1,5 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::ops::Deref;
 use std::rc::Rc;
 use crate::environment::Environment;
 use crate::interpreter::Interpreter;
@@ -12,11 +11,8 @@ use crate::value::LValue;
 use crate::value::condition::{*};
 
 impl Interpreter {
-    pub fn interpret_stmt_debug(&mut self, stmt: &Stmt) -> Result<(), RunTimeError> {
-        self.exectue(stmt)
-    }
-
     pub fn exectue(&mut self, stmt: &Stmt) -> Result<(), RunTimeError> {
+        self.budget.step()?;
         stmt.accept(self)
     }
 
@@ -30,42 +26,25 @@ impl StmtVistor<Result<(), RunTimeError>> for Interpreter {
         match root_stmt {
             Stmt::Print(expr) => {
                 let value = self.evaluate(expr)?;
-                println!("{:?}", value);
-                Ok(())
+                self.print(&value)
             },
             Stmt::Expr(expr) => {
                 self.evaluate(expr)?;
                 Ok(())
             },
             Stmt::Var(indentifier, expr) => {
-                let name = String::from_utf8(indentifier.lexeme.to_vec()).unwrap();
-
-                match expr {
-                    Some(expr) => {
-                        let value = self.evaluate(expr)?;
-                        self.environment.borrow_mut().define(name, value);
-                        Ok(())
-                    },
-                    None => {
-                        self.environment.borrow_mut().define(name, LValue::Nil);
-                        Ok(())
-                    }, 
-                }
-            },
-            Stmt::Block(statements) => {
-                let previous_environment = self.environment.clone();
-                let mut new_environment = Environment::new();
-                new_environment.enclosing = Some(previous_environment.clone());
-
-                self.environment = Rc::new(RefCell::new(new_environment));
-
-                for statement in statements {
-                    let value_result = self.exectue(statement)?;
-                }
+                let value = match expr {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => LValue::Nil,
+                };
 
-                self.environment = previous_environment;
+                self.define_variable(indentifier, value);
                 Ok(())
             },
+            Stmt::Block(_) => {
+                let new_environment = Environment::with_enclosing(self.environment.clone());
+                self.visit_env(root_stmt, Rc::new(RefCell::new(new_environment)))
+            },
             Stmt::If(condition, then_stmt, else_stmt) => {
                 let condition_val = self.evaluate(condition)?;
                 if condition_val.is_truthy() {
@@ -78,35 +57,90 @@ impl StmtVistor<Result<(), RunTimeError>> for Interpreter {
 
                 Ok(())
             },
-            Stmt::While(condition, body) => {
+            Stmt::While(condition, body, increment) => {
                 while self.evaluate(condition)?.is_truthy() {
-                    self.exectue(&(*body));
+                    self.budget.check(None)?;
+
+                    match self.exectue(body) {
+                        Ok(()) | Err(RunTimeError::Continue(_)) => {},
+                        Err(RunTimeError::Break(_)) => break,
+                        Err(error) => return Err(error),
+                    }
+
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
                 }
 
                 Ok(())
             },
             Stmt::Function(name, param, body) => {
-                let function_name = String::from_utf8(name.lexeme.to_vec()).unwrap();
                 let function_lvalue = LValue::Function(Rc::new(Function {
-                    params: param.to_vec(),
+                    params: param.clone(),
                     name: name.clone(),
-                    body: body.deref().clone(),
+                    body: body.clone(),
                     closure: self.environment.clone(),
                     is_initializer: false,
+                    class: None,
                 }));
 
-                self.environment.borrow_mut().define(function_name, function_lvalue);                
+                self.define_variable(name, function_lvalue);
                 Ok(())
             },
             Stmt::Return(_, value) => {
-                if value.is_some() {
-                    let return_value = self.evaluate((*value).as_ref().unwrap()).unwrap();
-                    // Throw error to pass the return value
-                    return Err(RunTimeError::Return(return_value));
+                let return_value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => LValue::Nil,
+                };
+
+                // Throw error to pass the return value
+                Err(RunTimeError::Return(return_value))
+            },
+            Stmt::Throw(token, value) => {
+                let value = self.evaluate(value)?;
+                Err(RunTimeError::thrown(value, token))
+            },
+            Stmt::Try(body, catch, finally) => {
+                let previous_environment = self.environment.clone();
+
+                let result = match (self.exectue(body), catch) {
+                    (Err(error), Some((_, handler))) => match error.into_caught() {
+                        Ok(value) => {
+                            self.traceback = None;
+
+                            // Blocks restore the environment they leave, make
+                            // sure nothing unwound past that is kept
+                            self.environment = previous_environment.clone();
+
+                            let mut catch_environment = Environment::with_enclosing(self.environment.clone());
+                            catch_environment.define(value);
+                            self.exectue_with_env(handler, Rc::new(RefCell::new(catch_environment)))
+                        },
+                        Err(error) => Err(error),
+                    },
+                    (result, _) => result,
+                };
+
+                if let Err(RunTimeError::Aborted(..)) = result {
+                    return result;
                 }
 
-                Ok(())
+                // A finally clause that completes normally keeps the outcome
+                // of the rest, return and throw inside it replace that
+                if let Some(finally) = finally {
+                    self.environment = previous_environment;
+
+                    // Errors the clause raises are traced on their own
+                    let traceback = self.traceback.take();
+                    self.exectue(finally)?;
+                    self.traceback = traceback;
+                }
+
+                result
             },
+            // Unwound to the innermost loop the same way as return
+            Stmt::Break(token) => Err(RunTimeError::Break(token.clone())),
+            Stmt::Continue(token) => Err(RunTimeError::Continue(token.clone())),
             Stmt::Class(name, supper_class, methods) => {
                 // TODO: Reconstruct the code here
                 let mut supper_class_val_option: Option<Rc<Class>> = None;
@@ -127,19 +161,12 @@ impl StmtVistor<Result<(), RunTimeError>> for Interpreter {
                 }
                 
                 let class_name = String::from_utf8(name.lexeme.to_vec()).unwrap();
-                self.environment.borrow_mut().define(class_name.clone(), LValue::Nil);
                 let previous_environment = self.environment.clone();
 
-                // Create env for super class method
-                // TODO: Reconstruct the code here
-                if let Some(supper_class_expr) = supper_class {
-                    let mut super_environment = Environment::new();
-
-                    if let Some(ref supper_class_val) = supper_class_val_option {
-                        super_environment.define(String::from("super"), LValue::Class(supper_class_val.clone()));
-                    }
-
-                    super_environment.enclosing = Some(self.environment.clone());
+                // Create env for super class method, `super` is its only slot
+                if let Some(ref supper_class_val) = supper_class_val_option {
+                    let mut super_environment = Environment::with_enclosing(self.environment.clone());
+                    super_environment.define(LValue::Class(supper_class_val.clone()));
                     self.environment = Rc::new(RefCell::new(super_environment));
                 }
 
@@ -152,10 +179,11 @@ impl StmtVistor<Result<(), RunTimeError>> for Interpreter {
                         let name_string = String::from_utf8(name.lexeme.to_vec()).unwrap();
                         let current_method = Rc::new(Function {
                             name: name.clone(),
-                            params: param.to_vec(),
-                            body: body.deref().clone(),
+                            params: param.clone(),
+                            body: body.clone(),
                             closure: self.environment.clone(),
                             is_initializer: name_string.eq(String::from("init").as_str()),
+                            class: Some(class_name.clone()),
                         });
 
                         methods_map.insert(name_string, current_method);
@@ -168,11 +196,11 @@ impl StmtVistor<Result<(), RunTimeError>> for Interpreter {
                     supper_class_val_option
                 ));
 
-                if let Some(supper_class_expr) = supper_class {
-                    self.environment = previous_environment;
-                }
+                self.environment = previous_environment;
 
-                self.environment.borrow_mut().assign(name, lclass)?;
+                // Methods look the class up only once called, so it can be
+                // defined after them, in the slot the resolver declared
+                self.define_variable(name, lclass);
                 Ok(())
             },
         }
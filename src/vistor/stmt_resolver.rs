@@ -3,9 +3,10 @@ use std::ops::Deref;
 use std::rc::Rc;
 use crate::environment::Environment;
 use crate::grammer::expression::Expr;
-use crate::resolver::{ClassStatus, FunctionStatus, Resolver, SetValueType};
+use crate::resolver::{Binding, ClassStatus, FunctionStatus, LoopStatus, Resolver};
 use crate::grammer::statement::{*};
 use crate::resolver::resolve_error::ResolveError;
+use crate::scanner::token::Token;
 
 impl Resolver<'_> {
     pub fn resolve_stmt(&mut self, stmt: &Stmt) -> Result<(), ResolveError> {
@@ -29,25 +30,35 @@ impl Resolver<'_> {
                 function_status_to_assign = FunctionStatus::Initializer;
             }
 
-            let previous_function_status = self.current_function;
-            self.current_function = function_status_to_assign;
+            self.resolve_function(argument, body, function_status_to_assign)?;
+        }
 
+        Ok(())
+    }
 
-            self.begin_scope();
+    /// Resolve the parameters and body of a named or anonymous function
+    pub fn resolve_function(&mut self, params: &[Token], body: &Stmt, function_status: FunctionStatus) -> Result<(), ResolveError> {
+        let previous_function_status = self.current_function;
+        self.current_function = function_status;
 
-            for token in argument {
-                self.declare(token)?;
-                self.define(token)?;
-            }
+        // Loops around a function can't be left from inside it
+        let previous_loop_status = self.current_loop;
+        self.current_loop = LoopStatus::None;
 
-            if let Stmt::Block(statmens) = body.deref() {
-                self.resolve_stmt_list(statmens)?;
-            }
+        self.begin_scope();
 
-            self.end_scope();
-            self.current_function = previous_function_status;
+        for token in params {
+            self.declare(token)?;
+            self.define(token)?;
         }
 
+        if let Stmt::Block(statmens) = body {
+            self.resolve_stmt_list(statmens)?;
+        }
+
+        self.end_scope();
+        self.current_function = previous_function_status;
+        self.current_loop = previous_loop_status;
         Ok(())
     }
 }
@@ -96,16 +107,16 @@ impl StmtVistor<Result<(), ResolveError>> for Resolver<'_> {
                 Ok(())
             },
             Stmt::Return(token, expr) => {
+                if self.current_function == FunctionStatus::None {
+                    return Err(ResolveError::CommonError {
+                        token: token.clone(),
+                        message: String::from("Can't return from top-level code."),
+                    });
+                }
+
                 if let Some(return_val) = expr {
                     self.resolve_expr(return_val)?;
 
-                    if self.current_function == FunctionStatus::None {
-                        return Err(ResolveError::CommonError {
-                            token: token.clone(),
-                            message: String::from("Can't return from top-level code."),
-                        });
-                    }
-
                     if self.current_function == FunctionStatus::Initializer {
                         return Err(ResolveError::CommonError {
                             token: token.clone(),
@@ -115,9 +126,52 @@ impl StmtVistor<Result<(), ResolveError>> for Resolver<'_> {
                 }
                 Ok(())
             },
-            Stmt::While(condition, body) => {
+            Stmt::While(condition, body, increment) => {
                 self.resolve_expr(condition)?;
+
+                let previous_loop_status = self.current_loop;
+                self.current_loop = LoopStatus::Loop;
+                self.resolve_stmt(body)?;
+                self.current_loop = previous_loop_status;
+
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment)?;
+                }
+                Ok(())
+            },
+            Stmt::Throw(_, value) => {
+                self.resolve_expr(value)?;
+                Ok(())
+            },
+            Stmt::Try(body, catch, finally) => {
                 self.resolve_stmt(body)?;
+
+                // The catch variable has a scope of its own, shared with
+                // the statements of the clause
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(name)?;
+                    self.define(name)?;
+
+                    if let Stmt::Block(statements) = handler.as_ref() {
+                        self.resolve_stmt_list(statements)?;
+                    }
+
+                    self.end_scope();
+                }
+
+                if let Some(finally) = finally {
+                    self.resolve_stmt(finally)?;
+                }
+                Ok(())
+            },
+            Stmt::Break(token) | Stmt::Continue(token) => {
+                if self.current_loop == LoopStatus::None {
+                    return Err(ResolveError::CommonError {
+                        token: token.clone(),
+                        message: format!("Can't use '{}' outside of a loop.", String::from_utf8_lossy(&token.lexeme)),
+                    });
+                }
                 Ok(())
             },
             Stmt::Class(name, supper_class, methods) => {
@@ -128,13 +182,13 @@ impl StmtVistor<Result<(), ResolveError>> for Resolver<'_> {
                 self.define(name)?;
 
                 if let Some(supper_class_expr) = supper_class {
-                    if let Expr::Variable(token) = supper_class_expr {
+                    if let Expr::Variable(token, _) = supper_class_expr {
                         if !name.lexeme.eq(&token.lexeme) {
                             self.resolve_expr(supper_class_expr)?;
 
                             // Add a new scope and insert super keyword
                             self.begin_scope();
-                            self.scopes.last_mut().unwrap().insert(String::from("super"), true);
+                            self.scopes.last_mut().unwrap().insert(String::from("super"), Binding::implicit(0));
                         } else {
                             return Err(ResolveError::CommonError {
                                 token: token.clone(),
@@ -151,7 +205,7 @@ impl StmtVistor<Result<(), ResolveError>> for Resolver<'_> {
 
                 self.begin_scope();
                 // Push this into the class scope
-                self.scopes.last_mut().unwrap().insert(String::from("this"), true);
+                self.scopes.last_mut().unwrap().insert(String::from("this"), Binding::implicit(0));
 
                 for method in methods {
                     self.resolve_stmt_function(method, FunctionStatus::Method)?;
@@ -161,7 +215,7 @@ impl StmtVistor<Result<(), ResolveError>> for Resolver<'_> {
                 self.end_scope();
 
                 // Exit super class scope
-                if let Some(supper_class_expr) = supper_class {
+                if let Some(_supper_class_expr) = supper_class {
                     self.end_scope();
                 }
 
@@ -170,11 +224,10 @@ impl StmtVistor<Result<(), ResolveError>> for Resolver<'_> {
 
                 Ok(())
             },
-            _ => Ok(()),
         }
     }
 
-    fn visit_env(&mut self, stmt: &Stmt, env: Rc<RefCell<Environment>>) -> Result<(), ResolveError> {
+    fn visit_env(&mut self, _stmt: &Stmt, _env: Rc<RefCell<Environment>>) -> Result<(), ResolveError> {
         Ok(())
     }
 }
\ No newline at end of file
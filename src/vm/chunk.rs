@@ -0,0 +1,162 @@
+use crate::environment::globals::Symbol;
+use crate::scanner::token::Token;
+use crate::value::LValue;
+
+/// Instructions of the virtual machine. Operands index the constant pool,
+/// a local slot of the current frame or an upvalue of the current closure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Constant(u16),
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal(u8),
+    SetLocal(u8),
+    GetGlobal(Symbol),
+    DefineGlobal(Symbol),
+    SetGlobal(Symbol),
+    GetUpvalue(u8),
+    SetUpvalue(u8),
+    // Operands of property instructions are the constant holding the name
+    GetProperty(u16),
+    SetProperty(u16),
+    GetSuper(u16),
+    // Operand is the number of elements on the stack
+    BuildList(u16),
+    // Operand is the number of key value pairs on the stack
+    BuildMap(u16),
+    // Operand is the number of parts on the stack, joined as printed
+    Interpolate(u16),
+    GetIndex,
+    SetIndex,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    FloorDivide,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Not,
+    Negate,
+    BitNot,
+    Print,
+    // Jump offsets are relative to the next instruction
+    Jump(u16),
+    JumpIfFalse(u16),
+    Loop(u16),
+    // Handlers of try statements, jumped to when an error is raised before
+    // the matching `EndTry`. `Try` gets the caught value on the stack and
+    // `TryFinally` the error being unwound.
+    Try(u16),
+    TryFinally(u16),
+    EndTry,
+    Throw,
+    Call(u8),
+    Closure(u16),
+    CloseUpvalue,
+    Return,
+    Class(u16),
+    Inherit,
+    Method(u16),
+}
+
+/// Run of instructions compiled from the same source token
+#[derive(Debug, Clone)]
+pub struct LineStart {
+    pub offset: usize,
+    pub token: Token,
+}
+
+#[derive(Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<LValue>,
+    // Run-length encoded, ordered by offset
+    pub lines: Vec<LineStart>,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk::default()
+    }
+
+    /// Append an instruction and return its offset
+    pub fn write(&mut self, op: Op, token: &Token) -> usize {
+        let same_token = self.lines.last().map(|line| line.token == *token).unwrap_or(false);
+
+        if !same_token {
+            self.lines.push(LineStart {
+                offset: self.code.len(),
+                token: token.clone(),
+            });
+        }
+
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    /// Add a constant and return its index, `None` when the pool is full
+    pub fn add_constant(&mut self, value: LValue) -> Option<u16> {
+        let index = u16::try_from(self.constants.len()).ok()?;
+        self.constants.push(value);
+        Some(index)
+    }
+
+    /// Source token the instruction at `offset` was compiled from
+    pub fn token_at(&self, offset: usize) -> Option<&Token> {
+        let index = self.lines.partition_point(|line| line.offset <= offset);
+        index.checked_sub(1).map(|index| &self.lines[index].token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::token::TokenType;
+
+    fn token(line: usize, col: usize) -> Token {
+        Token {
+            typee: TokenType::Identifier,
+            lexeme: String::from("a").into_bytes(),
+            literal: None,
+            line,
+            col,
+        }
+    }
+
+    #[test]
+    fn line_table() {
+        let mut chunk = Chunk::new();
+        chunk.write(Op::Nil, &token(1, 1));
+        chunk.write(Op::Pop, &token(1, 1));
+        chunk.write(Op::True, &token(2, 3));
+        chunk.write(Op::Pop, &token(4, 1));
+
+        assert_eq!(chunk.lines.len(), 3);
+        assert_eq!(chunk.token_at(0).map(|token| token.line), Some(1));
+        assert_eq!(chunk.token_at(1).map(|token| token.line), Some(1));
+        assert_eq!(chunk.token_at(2).map(|token| token.col), Some(3));
+        assert_eq!(chunk.token_at(3).map(|token| token.line), Some(4));
+    }
+
+    #[test]
+    fn constant_pool() {
+        let mut chunk = Chunk::new();
+
+        assert_eq!(chunk.add_constant(LValue::Number(1.0)), Some(0));
+        assert_eq!(chunk.add_constant(LValue::Nil), Some(1));
+        assert_eq!(chunk.constants[0], LValue::Number(1.0));
+    }
+}
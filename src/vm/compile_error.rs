@@ -0,0 +1,26 @@
+use std::fmt;
+use crate::diagnostic::{Diagnostic, Phase, Span};
+use crate::scanner::token::Token;
+
+/// Limits of the bytecode format that a program exceeded
+pub struct CompileError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl fmt::Debug for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Compile Error at line {}: {}",
+            self.token.line, self.message,
+        )
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Diagnostic {
+        Diagnostic::error(Phase::Compile, "E0401", error.message.clone())
+            .with_span(Span::from(&error.token))
+    }
+}
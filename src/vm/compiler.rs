@@ -0,0 +1,931 @@
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::rc::Rc;
+use crate::environment::Environment;
+use crate::environment::globals::Globals;
+use crate::grammer::expression::{Expr, ExprAccept, ExprLiteral, ExprVistor, Resolution};
+use crate::grammer::statement::{Stmt, StmtAccept, StmtVistor};
+use crate::resolver::FunctionStatus;
+use crate::scanner::token::{Token, TokenType};
+use crate::value::LValue;
+use super::chunk::{Chunk, Op};
+use super::compile_error::CompileError;
+use super::object::{FunctionProto, Object, UpvalueDesc};
+
+// Locals and upvalues are addressed with one byte
+const MAX_SLOTS: usize = 256;
+
+struct Local {
+    name: String,
+    depth: usize,
+    captured: bool,
+}
+
+// Jumps out of a loop being compiled, patched once its end is known
+struct Loop {
+    // Scope depth outside the body, deeper locals are dropped on the way out
+    depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// Try statement whose body is being compiled
+struct TryState {
+    // Run on the way out when jumping out of the body
+    finally: Option<Stmt>,
+    // Number of enclosing loops, jumps to them leave the statement
+    loops: usize,
+}
+
+// Class whose methods are being compiled
+struct ClassState {
+    name: String,
+    has_superclass: bool,
+}
+
+/// Function being compiled, the outermost one is the script itself
+struct FunctionState {
+    name: String,
+    arity: usize,
+    chunk: Chunk,
+    upvalues: Vec<UpvalueDesc>,
+    kind: FunctionStatus,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<TryState>,
+}
+
+impl FunctionState {
+    fn new(name: String, kind: FunctionStatus) -> FunctionState {
+        // Slot 0 holds the callee, which methods see as `this`
+        let slot_zero = match kind {
+            FunctionStatus::Method | FunctionStatus::Initializer => "this",
+            _ => "",
+        };
+
+        FunctionState {
+            name,
+            arity: 0,
+            chunk: Chunk::new(),
+            upvalues: Vec::new(),
+            kind,
+            locals: vec![Local { name: String::from(slot_zero), depth: 0, captured: false }],
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<u8> {
+        self.locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u8)
+    }
+}
+
+/// Compiles resolved statements into the bytecode of a script function
+pub struct Compiler<'a> {
+    globals: &'a mut Globals,
+    functions: Vec<FunctionState>,
+    classes: Vec<ClassState>,
+    // Token instructions are attributed to in the line table
+    token: Token,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(globals: &'a mut Globals) -> Compiler<'a> {
+        Compiler {
+            globals,
+            functions: vec![FunctionState::new(String::from("script"), FunctionStatus::None)],
+            classes: Vec::new(),
+            token: Token {
+                typee: TokenType::Eof,
+                lexeme: Vec::new(),
+                literal: None,
+                line: 1,
+                col: 1,
+            },
+        }
+    }
+
+    pub fn compile(mut self, statements: &[Stmt]) -> Result<Rc<FunctionProto>, CompileError> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+
+        self.emit_return();
+        Ok(self.finish_script())
+    }
+
+    /// Compile a lone expression into a script returning its value
+    pub fn compile_expr(mut self, expr: &Expr) -> Result<Rc<FunctionProto>, CompileError> {
+        self.expression(expr)?;
+        self.emit(Op::Return);
+        Ok(self.finish_script())
+    }
+
+    fn finish_script(&mut self) -> Rc<FunctionProto> {
+        let script = self.functions.pop().unwrap();
+
+        Rc::new(FunctionProto {
+            name: script.name,
+            class: None,
+            arity: 0,
+            chunk: script.chunk,
+            upvalues: script.upvalues,
+        })
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        stmt.accept(self)
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        expr.accept(self)
+    }
+
+    fn current(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn error(&self, message: &str) -> CompileError {
+        CompileError {
+            token: self.token.clone(),
+            message: String::from(message),
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        let token = self.token.clone();
+        self.current().chunk.write(op, &token)
+    }
+
+    fn emit_return(&mut self) {
+        if self.current().kind == FunctionStatus::Initializer {
+            self.emit(Op::GetLocal(0));
+        } else {
+            self.emit(Op::Nil);
+        }
+
+        self.emit(Op::Return);
+    }
+
+    fn make_constant(&mut self, value: LValue) -> Result<u16, CompileError> {
+        match self.current().chunk.add_constant(value) {
+            Some(index) => Ok(index),
+            None => Err(self.error("Too many constants in one chunk.")),
+        }
+    }
+
+    fn name_constant(&mut self, token: &Token) -> Result<u16, CompileError> {
+        self.make_constant(LValue::String(Compiler::name(token)))
+    }
+
+    fn name(token: &Token) -> String {
+        String::from_utf8_lossy(&token.lexeme).into_owned()
+    }
+
+    fn emit_jump(&mut self, op: fn(u16) -> Op) -> usize {
+        self.emit(op(0))
+    }
+
+    fn patch_jump(&mut self, offset: usize) -> Result<(), CompileError> {
+        let distance = self.current().chunk.code.len() - offset - 1;
+        let distance = u16::try_from(distance).map_err(|_| self.error("Too much code to jump over."))?;
+
+        let op = &mut self.current().chunk.code[offset];
+        *op = match op {
+            Op::Jump(_) => Op::Jump(distance),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(distance),
+            Op::Try(_) => Op::Try(distance),
+            Op::TryFinally(_) => Op::TryFinally(distance),
+            _ => *op,
+        };
+
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) -> Result<(), CompileError> {
+        let distance = self.current().chunk.code.len() + 1 - loop_start;
+        let distance = u16::try_from(distance).map_err(|_| self.error("Loop body too large."))?;
+        self.emit(Op::Loop(distance));
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.current().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.current().scope_depth -= 1;
+
+        loop {
+            let state = self.current();
+            let captured = match state.locals.last() {
+                Some(local) if local.depth > state.scope_depth => local.captured,
+                _ => break,
+            };
+
+            state.locals.pop();
+            self.emit(if captured { Op::CloseUpvalue } else { Op::Pop });
+        }
+    }
+
+    // Drop the locals of the scopes being jumped out of, without
+    // forgetting them as the code after the jump still sees them
+    fn discard_locals(&mut self, depth: usize) {
+        let state = self.current();
+        let ops: Vec<Op> = state.locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| if local.captured { Op::CloseUpvalue } else { Op::Pop })
+            .collect();
+
+        for op in ops {
+            self.emit(op);
+        }
+    }
+
+    // Slot on the stack for a value the compiled code keeps out of sight
+    fn hidden_local(&mut self) -> Result<u8, CompileError> {
+        self.begin_scope();
+        self.add_local(String::new())?;
+        Ok((self.current().locals.len() - 1) as u8)
+    }
+
+    // Drop a hidden local when the code that follows never reaches the end
+    // of its scope
+    fn forget_hidden_local(&mut self) {
+        let state = self.current();
+        state.locals.pop();
+        state.scope_depth -= 1;
+    }
+
+    // Leave the innermost `count` try statements before jumping out of
+    // them, running their finally clauses on the way
+    fn exit_tries(&mut self, count: usize) -> Result<(), CompileError> {
+        let mut exited: Vec<TryState> = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let state = self.current().tries.pop().unwrap();
+            self.emit(Op::EndTry);
+
+            if let Some(finally) = &state.finally {
+                self.statement(finally)?;
+            }
+
+            exited.push(state);
+        }
+
+        self.current().tries.extend(exited.into_iter().rev());
+        Ok(())
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.functions.len() == 1 && self.functions[0].scope_depth == 0
+    }
+
+    fn add_local(&mut self, name: String) -> Result<(), CompileError> {
+        if self.current().locals.len() >= MAX_SLOTS {
+            return Err(self.error("Too many local variables in function."));
+        }
+
+        let depth = self.current().scope_depth;
+        self.current().locals.push(Local { name, depth, captured: false });
+        Ok(())
+    }
+
+    /// Bind the value on top of the stack to a new variable
+    fn define_variable(&mut self, name: &Token) -> Result<(), CompileError> {
+        if self.is_global_scope() {
+            let symbol = self.globals.intern_token(name);
+            self.emit(Op::DefineGlobal(symbol));
+            Ok(())
+        } else {
+            // The value already sits in the slot of the new local
+            self.add_local(Compiler::name(name))
+        }
+    }
+
+    fn add_upvalue(&mut self, function: usize, index: u8, is_local: bool) -> Result<u8, CompileError> {
+        let upvalue = UpvalueDesc { is_local, index };
+        let upvalues = &mut self.functions[function].upvalues;
+
+        if let Some(existing) = upvalues.iter().position(|candidate| *candidate == upvalue) {
+            return Ok(existing as u8);
+        }
+
+        if upvalues.len() >= MAX_SLOTS {
+            return Err(self.error("Too many closure variables in function."));
+        }
+
+        upvalues.push(upvalue);
+        Ok((upvalues.len() - 1) as u8)
+    }
+
+    fn resolve_upvalue(&mut self, function: usize, name: &str) -> Result<Option<u8>, CompileError> {
+        if function == 0 {
+            return Ok(None);
+        }
+
+        let enclosing = function - 1;
+
+        if let Some(slot) = self.functions[enclosing].resolve_local(name) {
+            self.functions[enclosing].locals[slot as usize].captured = true;
+            return self.add_upvalue(function, slot, true).map(Some);
+        }
+
+        match self.resolve_upvalue(enclosing, name)? {
+            Some(index) => self.add_upvalue(function, index, false).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn named_variable(&mut self, name: &Token, resolution: &Cell<Resolution>, assign: bool) -> Result<(), CompileError> {
+        let name_string = Compiler::name(name);
+        let function = self.functions.len() - 1;
+
+        let op = if let Some(slot) = self.functions[function].resolve_local(&name_string) {
+            if assign { Op::SetLocal(slot) } else { Op::GetLocal(slot) }
+        } else if let Some(index) = self.resolve_upvalue(function, &name_string)? {
+            if assign { Op::SetUpvalue(index) } else { Op::GetUpvalue(index) }
+        } else {
+            let symbol = match resolution.get() {
+                Resolution::Global(symbol) => symbol,
+                _ => self.globals.intern(&name_string),
+            };
+
+            if assign { Op::SetGlobal(symbol) } else { Op::GetGlobal(symbol) }
+        };
+
+        self.token = name.clone();
+        self.emit(op);
+        Ok(())
+    }
+
+    fn function(&mut self, name: &Token, params: &[Token], body: &Stmt, kind: FunctionStatus) -> Result<(), CompileError> {
+        self.functions.push(FunctionState::new(Compiler::name(name), kind));
+        self.current().arity = params.len();
+        self.begin_scope();
+
+        for param in params {
+            self.token = param.clone();
+            self.add_local(Compiler::name(param))?;
+        }
+
+        // The body shares the scope of the params
+        if let Stmt::Block(statements) = body {
+            for statement in statements {
+                self.statement(statement)?;
+            }
+        }
+
+        self.emit_return();
+
+        // Locals die with the frame, so the scope is not ended
+        let state = self.functions.pop().unwrap();
+        let class = match kind {
+            FunctionStatus::Method | FunctionStatus::Initializer => self.classes.last().map(|class| class.name.clone()),
+            _ => None,
+        };
+
+        let proto = FunctionProto {
+            name: state.name,
+            class,
+            arity: state.arity,
+            chunk: state.chunk,
+            upvalues: state.upvalues,
+        };
+
+        self.token = name.clone();
+        let constant = self.make_constant(LValue::Object(Object::Function(Rc::new(proto))))?;
+        self.emit(Op::Closure(constant));
+        Ok(())
+    }
+}
+
+impl StmtVistor<Result<(), CompileError>> for Compiler<'_> {
+    fn visit(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.expression(expr)?;
+                self.emit(Op::Pop);
+                Ok(())
+            },
+            Stmt::Print(expr) => {
+                self.expression(expr)?;
+                self.emit(Op::Print);
+                Ok(())
+            },
+            Stmt::Var(name, initializer) => {
+                match initializer {
+                    Some(expr) => self.expression(expr)?,
+                    None => {
+                        self.token = name.clone();
+                        self.emit(Op::Nil);
+                    },
+                }
+
+                self.token = name.clone();
+                self.define_variable(name)
+            },
+            Stmt::Block(statements) => {
+                self.begin_scope();
+
+                for statement in statements {
+                    self.statement(statement)?;
+                }
+
+                self.end_scope();
+                Ok(())
+            },
+            Stmt::If(condition, then_stmt, else_stmt) => {
+                self.expression(condition)?;
+
+                let then_jump = self.emit_jump(Op::JumpIfFalse);
+                self.emit(Op::Pop);
+                self.statement(then_stmt)?;
+
+                let else_jump = self.emit_jump(Op::Jump);
+                self.patch_jump(then_jump)?;
+                self.emit(Op::Pop);
+
+                if let Some(else_stmt) = else_stmt.deref() {
+                    self.statement(else_stmt)?;
+                }
+
+                self.patch_jump(else_jump)
+            },
+            Stmt::While(condition, body, increment) => {
+                let loop_start = self.current().chunk.code.len();
+                self.expression(condition)?;
+
+                let exit_jump = self.emit_jump(Op::JumpIfFalse);
+                self.emit(Op::Pop);
+
+                let depth = self.current().scope_depth;
+                self.current().loops.push(Loop { depth, breaks: Vec::new(), continues: Vec::new() });
+                self.statement(body)?;
+                let body_loop = self.current().loops.pop().unwrap();
+
+                for continue_jump in body_loop.continues {
+                    self.patch_jump(continue_jump)?;
+                }
+
+                if let Some(increment) = increment {
+                    self.expression(increment)?;
+                    self.emit(Op::Pop);
+                }
+
+                self.emit_loop(loop_start)?;
+
+                self.patch_jump(exit_jump)?;
+                self.emit(Op::Pop);
+
+                for break_jump in body_loop.breaks {
+                    self.patch_jump(break_jump)?;
+                }
+
+                Ok(())
+            },
+            Stmt::Throw(token, value) => {
+                self.expression(value)?;
+                self.token = token.clone();
+                self.emit(Op::Throw);
+                Ok(())
+            },
+            Stmt::Try(body, catch, finally) => {
+                let loops = self.current().loops.len();
+
+                let finally_handler = match finally {
+                    Some(finally) => {
+                        let handler = self.emit_jump(Op::TryFinally);
+                        self.current().tries.push(TryState { finally: Some(finally.as_ref().clone()), loops });
+                        Some((handler, finally))
+                    },
+                    None => None,
+                };
+
+                match catch {
+                    Some((name, handler)) => {
+                        let catch_handler = self.emit_jump(Op::Try);
+                        self.current().tries.push(TryState { finally: None, loops });
+                        self.statement(body)?;
+                        self.current().tries.pop();
+                        self.emit(Op::EndTry);
+                        let end_jump = self.emit_jump(Op::Jump);
+
+                        // The caught value is pushed where the variable goes
+                        self.patch_jump(catch_handler)?;
+                        self.begin_scope();
+                        self.token = name.clone();
+                        self.add_local(Compiler::name(name))?;
+
+                        if let Stmt::Block(statements) = handler.as_ref() {
+                            for statement in statements {
+                                self.statement(statement)?;
+                            }
+                        }
+
+                        self.end_scope();
+                        self.patch_jump(end_jump)?;
+                    },
+                    None => self.statement(body)?,
+                }
+
+                if let Some((handler, finally)) = finally_handler {
+                    self.current().tries.pop();
+                    self.emit(Op::EndTry);
+                    self.statement(finally)?;
+                    let end_jump = self.emit_jump(Op::Jump);
+
+                    // Unwinding runs the clause with the error in a hidden
+                    // local, then raises it again
+                    self.patch_jump(handler)?;
+                    self.hidden_local()?;
+                    self.statement(finally)?;
+                    self.emit(Op::Throw);
+                    self.forget_hidden_local();
+                    self.patch_jump(end_jump)?;
+                }
+
+                Ok(())
+            },
+            Stmt::Break(token) | Stmt::Continue(token) => {
+                self.token = token.clone();
+
+                let depth = match self.current().loops.last() {
+                    Some(body_loop) => body_loop.depth,
+                    None => return Err(self.error("Can't jump outside of a loop.")),
+                };
+
+                let loops = self.current().loops.len();
+                let count = self.current().tries.iter().rev().take_while(|state| state.loops == loops).count();
+                self.exit_tries(count)?;
+                self.token = token.clone();
+
+                self.discard_locals(depth);
+                let jump = self.emit_jump(Op::Jump);
+                let body_loop = self.current().loops.last_mut().unwrap();
+
+                if matches!(stmt, Stmt::Break(_)) {
+                    body_loop.breaks.push(jump);
+                } else {
+                    body_loop.continues.push(jump);
+                }
+
+                Ok(())
+            },
+            Stmt::Function(name, params, body) => {
+                // Declared before the body so that it can call itself
+                self.token = name.clone();
+                if !self.is_global_scope() {
+                    self.add_local(Compiler::name(name))?;
+                }
+
+                self.function(name, params, body, FunctionStatus::Function)?;
+
+                if self.is_global_scope() {
+                    let symbol = self.globals.intern_token(name);
+                    self.emit(Op::DefineGlobal(symbol));
+                }
+
+                Ok(())
+            },
+            Stmt::Return(token, value) => {
+                self.token = token.clone();
+
+                let count = self.current().tries.len();
+
+                match value {
+                    Some(value) if self.current().kind != FunctionStatus::Initializer => {
+                        self.expression(value)?;
+                        self.token = token.clone();
+
+                        if count == 0 {
+                            self.emit(Op::Return);
+                            return Ok(());
+                        }
+
+                        // The value waits for the finally clauses to run
+                        let slot = self.hidden_local()?;
+                        self.exit_tries(count)?;
+                        self.token = token.clone();
+                        self.emit(Op::GetLocal(slot));
+                        self.emit(Op::Return);
+                        self.forget_hidden_local();
+                    },
+                    _ => {
+                        self.exit_tries(count)?;
+                        self.token = token.clone();
+                        self.emit_return();
+                    },
+                }
+
+                Ok(())
+            },
+            Stmt::Class(name, superclass, methods) => {
+                self.token = name.clone();
+                let name_constant = self.name_constant(name)?;
+                let unresolved = Cell::new(Resolution::Unresolved);
+
+                self.emit(Op::Class(name_constant));
+                self.define_variable(name)?;
+
+                if let Some(superclass) = superclass {
+                    self.expression(superclass)?;
+
+                    // The superclass stays on the stack as the local `super`
+                    self.begin_scope();
+                    self.add_local(String::from("super"))?;
+
+                    self.named_variable(name, &unresolved, false)?;
+                    self.emit(Op::Inherit);
+                }
+
+                self.classes.push(ClassState {
+                    name: Compiler::name(name),
+                    has_superclass: superclass.is_some(),
+                });
+                self.named_variable(name, &unresolved, false)?;
+
+                for method in methods {
+                    if let Stmt::Function(method_name, params, body) = method {
+                        let kind = if method_name.lexeme == b"init" {
+                            FunctionStatus::Initializer
+                        } else {
+                            FunctionStatus::Method
+                        };
+
+                        self.function(method_name, params, body, kind)?;
+                        let method_constant = self.name_constant(method_name)?;
+                        self.emit(Op::Method(method_constant));
+                    }
+                }
+
+                self.emit(Op::Pop);
+
+                if self.classes.pop().is_some_and(|class| class.has_superclass) {
+                    self.end_scope();
+                }
+
+                Ok(())
+            },
+        }
+    }
+
+    fn visit_env(&mut self, _stmt: &Stmt, _env: Rc<RefCell<Environment>>) -> Result<(), CompileError> {
+        Ok(())
+    }
+}
+
+impl ExprVistor<Result<(), CompileError>> for Compiler<'_> {
+    fn visit(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Literal(literal) => {
+                match literal {
+                    ExprLiteral::Nil => self.emit(Op::Nil),
+                    ExprLiteral::True => self.emit(Op::True),
+                    ExprLiteral::False => self.emit(Op::False),
+                    ExprLiteral::Number(n) => {
+                        let constant = self.make_constant(LValue::Number(*n))?;
+                        self.emit(Op::Constant(constant))
+                    },
+                    ExprLiteral::String(s) => {
+                        let constant = self.make_constant(LValue::String(s.clone()))?;
+                        self.emit(Op::Constant(constant))
+                    },
+                };
+                Ok(())
+            },
+            Expr::Grouping(expr) => self.expression(expr),
+            Expr::Unary(token, expr) => {
+                self.expression(expr)?;
+                self.token = token.clone();
+
+                match token.typee {
+                    TokenType::Minus => self.emit(Op::Negate),
+                    TokenType::Tilde => self.emit(Op::BitNot),
+                    _ => self.emit(Op::Not),
+                };
+                Ok(())
+            },
+            Expr::Binary(left, token, right) => {
+                self.expression(left)?;
+                self.expression(right)?;
+                self.token = token.clone();
+
+                let op = match token.typee {
+                    TokenType::Plus => Op::Add,
+                    TokenType::Minus => Op::Subtract,
+                    TokenType::Star => Op::Multiply,
+                    TokenType::Slash => Op::Divide,
+                    TokenType::Percent => Op::Modulo,
+                    TokenType::TildeSlash => Op::FloorDivide,
+                    TokenType::StarStar => Op::Power,
+                    TokenType::Ampersand => Op::BitAnd,
+                    TokenType::Pipe => Op::BitOr,
+                    TokenType::Caret => Op::BitXor,
+                    TokenType::LessLess => Op::ShiftLeft,
+                    TokenType::GreaterGreater => Op::ShiftRight,
+                    TokenType::Greater => Op::Greater,
+                    TokenType::GreaterEqual => Op::GreaterEqual,
+                    TokenType::Less => Op::Less,
+                    TokenType::LessEqual => Op::LessEqual,
+                    TokenType::BangEqual => Op::NotEqual,
+                    TokenType::EqualEqual => Op::Equal,
+                    _ => return Err(self.error("Unknown binary operator.")),
+                };
+
+                self.emit(op);
+                Ok(())
+            },
+            Expr::Variable(name, resolution) => self.named_variable(name, resolution, false),
+            Expr::Assign(name, value, resolution) => {
+                self.expression(value)?;
+                self.named_variable(name, resolution, true)
+            },
+            Expr::Logical(left, token, right) => {
+                self.expression(left)?;
+                self.token = token.clone();
+
+                if token.typee == TokenType::And {
+                    let end_jump = self.emit_jump(Op::JumpIfFalse);
+                    self.emit(Op::Pop);
+                    self.expression(right)?;
+                    self.patch_jump(end_jump)
+                } else {
+                    let else_jump = self.emit_jump(Op::JumpIfFalse);
+                    let end_jump = self.emit_jump(Op::Jump);
+                    self.patch_jump(else_jump)?;
+                    self.emit(Op::Pop);
+                    self.expression(right)?;
+                    self.patch_jump(end_jump)
+                }
+            },
+            Expr::Call(callee, paren, arguments) => {
+                self.expression(callee)?;
+
+                for argument in arguments {
+                    self.expression(argument)?;
+                }
+
+                self.token = paren.clone();
+                self.emit(Op::Call(arguments.len() as u8));
+                Ok(())
+            },
+            Expr::New(_, call) => self.expression(call),
+            Expr::Get(object, name) => {
+                self.expression(object)?;
+                self.token = name.clone();
+                let constant = self.name_constant(name)?;
+                self.emit(Op::GetProperty(constant));
+                Ok(())
+            },
+            Expr::Set(object, name, value) => {
+                self.expression(object)?;
+                self.expression(value)?;
+                self.token = name.clone();
+                let constant = self.name_constant(name)?;
+                self.emit(Op::SetProperty(constant));
+                Ok(())
+            },
+            Expr::This(token, resolution) => self.named_variable(token, resolution, false),
+            Expr::Lambda(keyword, params, body) => self.function(keyword, params, body, FunctionStatus::Function),
+            Expr::Interpolation(start, parts) => {
+                for part in parts {
+                    self.expression(part)?;
+                }
+
+                self.token = start.clone();
+                let count = u16::try_from(parts.len()).map_err(|_| self.error("Too many parts in interpolated string."))?;
+                self.emit(Op::Interpolate(count));
+                Ok(())
+            },
+            Expr::List(bracket, elements) => {
+                for element in elements {
+                    self.expression(element)?;
+                }
+
+                self.token = bracket.clone();
+                let count = u16::try_from(elements.len()).map_err(|_| self.error("Too many elements in list literal."))?;
+                self.emit(Op::BuildList(count));
+                Ok(())
+            },
+            Expr::Map(brace, pairs) => {
+                for (key, value) in pairs {
+                    self.expression(key)?;
+                    self.expression(value)?;
+                }
+
+                self.token = brace.clone();
+                let count = u16::try_from(pairs.len()).map_err(|_| self.error("Too many entries in map literal."))?;
+                self.emit(Op::BuildMap(count));
+                Ok(())
+            },
+            Expr::Index(object, bracket, index) => {
+                self.expression(object)?;
+                self.expression(index)?;
+                self.token = bracket.clone();
+                self.emit(Op::GetIndex);
+                Ok(())
+            },
+            Expr::SetIndex(object, bracket, index, value) => {
+                self.expression(object)?;
+                self.expression(index)?;
+                self.expression(value)?;
+                self.token = bracket.clone();
+                self.emit(Op::SetIndex);
+                Ok(())
+            },
+            Expr::Super(token, method, _) => {
+                let this_token = Token {
+                    typee: TokenType::This,
+                    lexeme: String::from("this").into_bytes(),
+                    ..token.clone()
+                };
+                let unresolved = Cell::new(Resolution::Unresolved);
+
+                self.named_variable(&this_token, &unresolved, false)?;
+                self.named_variable(token, &unresolved, false)?;
+
+                if let Expr::Variable(method_name, _) = method.deref() {
+                    let constant = self.name_constant(method_name)?;
+                    self.token = method_name.clone();
+                    self.emit(Op::GetSuper(constant));
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    fn compile(source: &str) -> Result<Rc<FunctionProto>, CompileError> {
+        let mut interpreter = Interpreter::new();
+        let tokens = Scanner::new(String::from(source)).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parser().unwrap();
+        Resolver::new(&mut interpreter).resolve(&statements).unwrap();
+        Compiler::new(&mut interpreter.globals).compile(&statements)
+    }
+
+    #[test]
+    fn compile_locals_and_jumps() {
+        let script = compile("{ var a = 1; if (a) print a; }").unwrap();
+
+        assert_eq!(script.chunk.code, vec![
+            Op::Constant(0),
+            Op::GetLocal(1),
+            Op::JumpIfFalse(4),
+            Op::Pop,
+            Op::GetLocal(1),
+            Op::Print,
+            Op::Jump(1),
+            Op::Pop,
+            Op::Pop,
+            Op::Nil,
+            Op::Return,
+        ]);
+        assert_eq!(script.chunk.constants, vec![LValue::Number(1.0)]);
+    }
+
+    #[test]
+    fn compile_upvalues() {
+        let script = compile("fun outer() { var x = 1; fun inner() { return x; } return inner; }").unwrap();
+
+        let outer = match &script.chunk.constants[0] {
+            LValue::Object(Object::Function(function)) => function.clone(),
+            _ => panic!("expected a function constant"),
+        };
+        let inner = outer.chunk.constants.iter().find_map(|constant| match constant {
+            LValue::Object(Object::Function(function)) => Some(function.clone()),
+            _ => None,
+        }).unwrap();
+
+        assert_eq!(inner.upvalues, vec![UpvalueDesc { is_local: true, index: 1 }]);
+        assert_eq!(inner.chunk.code[0], Op::GetUpvalue(0));
+    }
+
+    #[test]
+    fn too_many_locals() {
+        let declarations: String = (0..300).map(|i| format!("var a{} = nil;", i)).collect();
+        let error = match compile(&format!("{{ {} }}", declarations)) {
+            Err(error) => error,
+            Ok(_) => panic!("expected a compile error"),
+        };
+
+        assert_eq!(error.message, "Too many local variables in function.");
+        assert_eq!(error.token.lexeme, b"a255");
+    }
+}
@@ -0,0 +1,683 @@
+pub mod chunk;
+pub mod compiler;
+pub mod compile_error;
+pub mod object;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::interpreter::Interpreter;
+use crate::interpreter::trace::TraceFrame;
+use crate::runner::error::{CommonError, RunTimeError};
+use crate::scanner::token::Token;
+use crate::value::LValue;
+use crate::value::condition::IsTruthy;
+use crate::value::function::Callable;
+use crate::value::list::List;
+use crate::value::map::{Map, MapKey};
+use chunk::Op;
+use object::{BoundMethod, Closure, FunctionProto, Instance, ObjClass, Object, Upvalue};
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    // Stack index of slot 0, which holds the callee or `this`
+    base: usize,
+}
+
+// Handler of a try statement being run
+struct Handler {
+    // Frames and stack to unwind to
+    frames: usize,
+    stack: usize,
+    ip: usize,
+    // Whether it is a finally clause, which gets the error as it is
+    finally: bool,
+}
+
+/// Stack based virtual machine running compiled scripts. It shares the
+/// globals and native functions of the interpreter it is created with.
+pub struct Vm<'a> {
+    interpreter: &'a mut Interpreter,
+    stack: Vec<LValue>,
+    frames: Vec<CallFrame>,
+    // Upvalues still pointing at the stack
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    handlers: Vec<Handler>,
+}
+
+fn runtime_error(token: &Token, message: String) -> RunTimeError {
+    RunTimeError::Error(CommonError {
+        token: Some(token.clone()),
+        message,
+    })
+}
+
+// Every instruction is written along with its token
+fn source(function: &FunctionProto, ip: usize) -> &Token {
+    function.chunk.token_at(ip).unwrap()
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Vm<'a> {
+        Vm {
+            interpreter,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            open_upvalues: Vec::new(),
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Run a compiled script and give back the value it returns
+    pub fn run(&mut self, script: Rc<FunctionProto>) -> Result<LValue, RunTimeError> {
+        let closure = Rc::new(Closure {
+            function: script,
+            upvalues: Vec::new(),
+        });
+
+        self.stack.push(LValue::Object(Object::Closure(closure.clone())));
+        self.frames.push(CallFrame { closure, ip: 0, base: 0 });
+        self.interpreter.budget.start();
+
+        let result = self.execute().map(|_| self.pop());
+
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+            self.handlers.clear();
+        }
+
+        result
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().unwrap()
+    }
+
+    fn push(&mut self, value: LValue) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> LValue {
+        self.stack.pop().unwrap()
+    }
+
+    fn peek(&self, distance: usize) -> &LValue {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn binary(&mut self, operation: fn(LValue, LValue) -> Result<LValue, String>, token: &Token) -> Result<(), RunTimeError> {
+        let right = self.pop();
+        let left = self.pop();
+        let value = Interpreter::result_expr_helper(operation(left, right), token)?;
+        self.push(value);
+        Ok(())
+    }
+
+    fn compare(&mut self, comparison: fn(&LValue, &LValue) -> bool) {
+        let right = self.pop();
+        let left = self.pop();
+        self.push(LValue::Bool(comparison(&left, &right)));
+    }
+
+    fn execute(&mut self) -> Result<(), RunTimeError> {
+        loop {
+            match self.dispatch() {
+                Ok(()) => return Ok(()),
+                Err(error) => self.unwind(error)?,
+            }
+        }
+    }
+
+    // Resume at the innermost handler, or give the error back when there
+    // is none left
+    fn unwind(&mut self, error: RunTimeError) -> Result<(), RunTimeError> {
+        if let RunTimeError::Aborted(..) = error {
+            self.handlers.clear();
+        }
+
+        let handler = match self.handlers.pop() {
+            Some(handler) => handler,
+            None => {
+                self.interpreter.traceback = Some(self.traceback());
+                return Err(error);
+            },
+        };
+
+        let value = if handler.finally {
+            LValue::Object(Object::Unwinding(Rc::new(error)))
+        } else {
+            error.into_caught()?
+        };
+
+        self.frames.truncate(handler.frames);
+        self.close_upvalues(handler.stack);
+        self.stack.truncate(handler.stack);
+        self.push(value);
+        self.frames.last_mut().unwrap().ip = handler.ip;
+        Ok(())
+    }
+
+    // Frames being run, innermost first, each at the instruction it is
+    // running or the call it waits on
+    fn traceback(&self) -> Vec<TraceFrame> {
+        self.frames.iter().enumerate().rev().map(|(depth, frame)| {
+            let function = &frame.closure.function;
+            let line = function.chunk.token_at(frame.ip - 1).map(|token| token.line);
+
+            match depth {
+                0 => TraceFrame::script(line),
+                _ => TraceFrame { function: function.qualified_name(), line },
+            }
+        }).collect()
+    }
+
+    fn dispatch(&mut self) -> Result<(), RunTimeError> {
+        let mut function = self.frame().closure.function.clone();
+
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let ip = frame.ip;
+            let base = frame.base;
+            frame.ip += 1;
+
+            let op = function.chunk.code[ip];
+            self.interpreter.budget.step()?;
+
+            match op {
+                Op::Constant(index) => self.push(function.chunk.constants[index as usize].clone()),
+                Op::Nil => self.push(LValue::Nil),
+                Op::True => self.push(LValue::Bool(true)),
+                Op::False => self.push(LValue::Bool(false)),
+                Op::Pop => {
+                    self.pop();
+                },
+                Op::GetLocal(slot) => {
+                    let value = self.stack[base + slot as usize].clone();
+                    self.push(value);
+                },
+                Op::SetLocal(slot) => {
+                    self.stack[base + slot as usize] = self.peek(0).clone();
+                },
+                Op::GetGlobal(symbol) => {
+                    let value = self.interpreter.globals.get(symbol, source(&function, ip))?;
+                    self.push(value);
+                },
+                Op::DefineGlobal(symbol) => {
+                    let value = self.pop();
+                    self.interpreter.globals.define(symbol, value);
+                },
+                Op::SetGlobal(symbol) => {
+                    let value = self.peek(0).clone();
+                    self.interpreter.globals.assign(symbol, source(&function, ip), value)?;
+                },
+                Op::GetUpvalue(index) => {
+                    let upvalue = self.frame().closure.upvalues[index as usize].clone();
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                },
+                Op::SetUpvalue(index) => {
+                    let upvalue = self.frame().closure.upvalues[index as usize].clone();
+                    let value = self.peek(0).clone();
+                    let mut upvalue = upvalue.borrow_mut();
+
+                    match &mut *upvalue {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                },
+                Op::GetProperty(index) => {
+                    let name = Vm::name(&function, index);
+                    let token = source(&function, ip);
+
+                    let instance = match self.pop() {
+                        LValue::Object(Object::Instance(instance)) => instance,
+                        LValue::Exception(exception) => {
+                            let value = exception.get(token)?;
+                            self.push(value);
+                            continue;
+                        },
+                        _ => return Err(runtime_error(token, String::from("Only instances have fields."))),
+                    };
+
+                    let field = instance.fields.borrow().get(&name).cloned();
+                    let value = match field {
+                        Some(value) => value,
+                        None => {
+                            let receiver = LValue::Object(Object::Instance(instance.clone()));
+                            Vm::bind_method(&instance.class, &name, receiver, token)?
+                        },
+                    };
+                    self.push(value);
+                },
+                Op::SetProperty(index) => {
+                    let value = self.pop();
+
+                    match self.pop() {
+                        LValue::Object(Object::Instance(instance)) => {
+                            instance.fields.borrow_mut().insert(Vm::name(&function, index), value.clone());
+                            self.push(value);
+                        },
+                        _ => {
+                            let token = source(&function, ip);
+                            return Err(runtime_error(token, String::from("Only instances have fields.")));
+                        },
+                    }
+                },
+                Op::GetSuper(index) => {
+                    let superclass = self.pop();
+                    let receiver = self.pop();
+
+                    if let LValue::Object(Object::Class(superclass)) = superclass {
+                        let method = Vm::bind_method(&superclass, &Vm::name(&function, index), receiver, source(&function, ip))?;
+                        self.push(method);
+                    }
+                },
+                Op::Interpolate(count) => {
+                    let parts = self.stack.split_off(self.stack.len() - count as usize);
+                    let text: String = parts.iter().map(LValue::to_string).collect();
+                    self.push(LValue::String(text));
+                },
+                Op::BuildList(count) => {
+                    let elements = self.stack.split_off(self.stack.len() - count as usize);
+                    self.push(LValue::List(List::new(elements)));
+                },
+                Op::BuildMap(count) => {
+                    let mut entries = self.stack.split_off(self.stack.len() - 2 * count as usize).into_iter();
+                    let mut pairs = Vec::with_capacity(count as usize);
+
+                    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+                        let key = MapKey::from_value(&key)
+                            .map_err(|message| runtime_error(source(&function, ip), message))?;
+                        pairs.push((key, value));
+                    }
+
+                    self.push(LValue::Map(Map::new(pairs)));
+                },
+                Op::GetIndex => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = object.get_index(&index, source(&function, ip))?;
+                    self.push(value);
+                },
+                Op::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+                    object.set_index(&index, source(&function, ip), value.clone())?;
+                    self.push(value);
+                },
+                Op::Equal => self.compare(|left, right| left == right),
+                Op::NotEqual => self.compare(|left, right| left != right),
+                Op::Greater => self.compare(|left, right| left > right),
+                Op::GreaterEqual => self.compare(|left, right| left >= right),
+                Op::Less => self.compare(|left, right| left < right),
+                Op::LessEqual => self.compare(|left, right| left <= right),
+                Op::Add => self.binary(|left, right| left + right, source(&function, ip))?,
+                Op::Subtract => self.binary(|left, right| left - right, source(&function, ip))?,
+                Op::Multiply => self.binary(|left, right| left * right, source(&function, ip))?,
+                Op::Divide => self.binary(|left, right| left / right, source(&function, ip))?,
+                Op::Modulo => self.binary(|left, right| left % right, source(&function, ip))?,
+                Op::FloorDivide => self.binary(LValue::floor_div, source(&function, ip))?,
+                Op::Power => self.binary(LValue::pow, source(&function, ip))?,
+                Op::BitAnd => self.binary(|left, right| left & right, source(&function, ip))?,
+                Op::BitOr => self.binary(|left, right| left | right, source(&function, ip))?,
+                Op::BitXor => self.binary(|left, right| left ^ right, source(&function, ip))?,
+                Op::ShiftLeft => self.binary(|left, right| left << right, source(&function, ip))?,
+                Op::ShiftRight => self.binary(|left, right| left >> right, source(&function, ip))?,
+                Op::Not => {
+                    let value = self.pop();
+                    let value = Interpreter::result_expr_helper(!value, source(&function, ip))?;
+                    self.push(value);
+                },
+                Op::Negate => {
+                    let value = self.pop();
+                    let value = Interpreter::result_expr_helper(-value, source(&function, ip))?;
+                    self.push(value);
+                },
+                Op::BitNot => {
+                    let value = self.pop();
+                    let value = Interpreter::result_expr_helper(value.bit_not(), source(&function, ip))?;
+                    self.push(value);
+                },
+                Op::Print => {
+                    let value = self.pop();
+                    self.interpreter.print(&value)?;
+                },
+                Op::Jump(offset) => {
+                    self.frames.last_mut().unwrap().ip += offset as usize;
+                },
+                Op::JumpIfFalse(offset) => {
+                    if !self.peek(0).is_truthy() {
+                        self.frames.last_mut().unwrap().ip += offset as usize;
+                    }
+                },
+                Op::Loop(offset) => {
+                    self.interpreter.budget.check(None)?;
+                    self.frames.last_mut().unwrap().ip -= offset as usize;
+                },
+                Op::Try(offset) | Op::TryFinally(offset) => {
+                    self.handlers.push(Handler {
+                        frames: self.frames.len(),
+                        stack: self.stack.len(),
+                        ip: ip + 1 + offset as usize,
+                        finally: matches!(op, Op::TryFinally(_)),
+                    });
+                },
+                Op::EndTry => {
+                    self.handlers.pop();
+                },
+                Op::Throw => {
+                    return Err(match self.pop() {
+                        LValue::Object(Object::Unwinding(error)) => error.as_ref().clone(),
+                        value => RunTimeError::thrown(value, source(&function, ip)),
+                    });
+                },
+                Op::Call(count) => {
+                    self.call_value(count as usize, source(&function, ip))?;
+                    function = self.frame().closure.function.clone();
+                },
+                Op::Closure(index) => {
+                    if let LValue::Object(Object::Function(proto)) = &function.chunk.constants[index as usize] {
+                        let mut upvalues = Vec::with_capacity(proto.upvalues.len());
+
+                        for upvalue in proto.upvalues.iter() {
+                            if upvalue.is_local {
+                                upvalues.push(self.capture_upvalue(base + upvalue.index as usize));
+                            } else {
+                                upvalues.push(self.frame().closure.upvalues[upvalue.index as usize].clone());
+                            }
+                        }
+
+                        let closure = Closure {
+                            function: proto.clone(),
+                            upvalues,
+                        };
+                        self.push(LValue::Object(Object::Closure(Rc::new(closure))));
+                    }
+                },
+                Op::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                },
+                Op::Return => {
+                    let result = self.pop();
+                    self.close_upvalues(base);
+                    self.frames.pop();
+
+                    self.stack.truncate(base);
+                    self.push(result);
+
+                    // Returning from the script leaves its value on the stack
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+
+                    function = self.frame().closure.function.clone();
+                },
+                Op::Class(index) => {
+                    let class = ObjClass {
+                        name: Vm::name(&function, index),
+                        methods: RefCell::new(HashMap::new()),
+                    };
+                    self.push(LValue::Object(Object::Class(Rc::new(class))));
+                },
+                Op::Inherit => {
+                    let subclass = self.pop();
+
+                    match (self.peek(0), subclass) {
+                        (LValue::Object(Object::Class(superclass)), LValue::Object(Object::Class(subclass))) => {
+                            // Copied down, methods of the subclass override them later
+                            let methods = superclass.methods.borrow().clone();
+                            subclass.methods.borrow_mut().extend(methods);
+                        },
+                        _ => {
+                            let token = source(&function, ip);
+                            return Err(runtime_error(token, String::from("Supperclass must be a class")));
+                        },
+                    }
+                },
+                Op::Method(index) => {
+                    let method = self.pop();
+
+                    if let (LValue::Object(Object::Class(class)), LValue::Object(Object::Closure(method))) = (self.peek(0), method) {
+                        class.methods.borrow_mut().insert(Vm::name(&function, index), method);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Name held by a string constant
+    fn name(function: &FunctionProto, index: u16) -> String {
+        match &function.chunk.constants[index as usize] {
+            LValue::String(name) => name.clone(),
+            _ => String::new(),
+        }
+    }
+
+    fn bind_method(class: &ObjClass, name: &str, receiver: LValue, token: &Token) -> Result<LValue, RunTimeError> {
+        match class.methods.borrow().get(name) {
+            Some(method) => {
+                let bound = BoundMethod {
+                    receiver,
+                    method: method.clone(),
+                };
+                Ok(LValue::Object(Object::BoundMethod(Rc::new(bound))))
+            },
+            None => Err(runtime_error(token, format!("Undefined property {}", name))),
+        }
+    }
+
+    fn call_value(&mut self, count: usize, token: &Token) -> Result<(), RunTimeError> {
+        let callee_slot = self.stack.len() - 1 - count;
+
+        match self.stack[callee_slot].clone() {
+            LValue::Object(Object::Closure(closure)) => self.call(closure, count, token),
+            LValue::Object(Object::BoundMethod(bound)) => {
+                self.stack[callee_slot] = bound.receiver.clone();
+                self.call(bound.method.clone(), count, token)
+            },
+            LValue::Object(Object::Class(class)) => {
+                let instance = Instance {
+                    class: class.clone(),
+                    fields: RefCell::new(HashMap::new()),
+                };
+                self.stack[callee_slot] = LValue::Object(Object::Instance(Rc::new(instance)));
+
+                let initializer = class.methods.borrow().get("init").cloned();
+                match initializer {
+                    Some(initializer) => self.call(initializer, count, token),
+                    None if count != 0 => Err(Vm::arity_error(0, count, token)),
+                    // The instance is left in place of the class
+                    None => Ok(()),
+                }
+            },
+            LValue::NativeFunction(native) => {
+                if native.arity != count {
+                    return Err(Vm::arity_error(native.arity, count, token));
+                }
+
+                let arguments = self.stack.split_off(callee_slot + 1);
+                self.pop();
+
+                let value = native.call(self.interpreter, token, arguments)?;
+                self.push(value);
+                Ok(())
+            },
+            _ => Err(runtime_error(token, String::from("Can only call functions and classes."))),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, count: usize, token: &Token) -> Result<(), RunTimeError> {
+        if closure.function.arity != count {
+            return Err(Vm::arity_error(closure.function.arity, count, token));
+        }
+
+        // The frame of the script is not a call
+        if self.frames.len() > self.interpreter.max_call_depth {
+            return Err(self.interpreter.stack_overflow(&closure.function.qualified_name(), token));
+        }
+
+        self.interpreter.budget.check(Some(token))?;
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: self.stack.len() - count - 1,
+        });
+        Ok(())
+    }
+
+    fn arity_error(arity: usize, count: usize, token: &Token) -> RunTimeError {
+        runtime_error(token, format!("Expected {} arguments but got {}.", arity, count))
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self.open_upvalues.iter().find(|upvalue| {
+            matches!(&*upvalue.borrow(), Upvalue::Open(open) if *open == slot)
+        });
+
+        if let Some(upvalue) = existing {
+            return upvalue.clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    /// Move variables at or above `from` off the stack into their upvalues
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+
+        self.open_upvalues.retain(|upvalue| {
+            let slot = match &*upvalue.borrow() {
+                Upvalue::Open(slot) => *slot,
+                Upvalue::Closed(_) => return false,
+            };
+
+            if slot < from {
+                return true;
+            }
+
+            *upvalue.borrow_mut() = Upvalue::Closed(stack[slot].clone());
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::testing::{self, global};
+    use crate::runner::options::Engine;
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), RunTimeError> {
+        testing::run(Engine::Vm, interpreter, source)
+    }
+
+    #[test]
+    fn closed_upvalues() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var get;
+            var set;
+            {
+                var shared = 1;
+                fun getter() { return shared; }
+                fun setter(value) { shared = value; }
+                get = getter;
+                set = setter;
+            }
+            set(2);
+            var result = get();
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "result"), LValue::Number(2.0));
+    }
+
+    #[test]
+    fn classes_and_super() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            class A {
+                init(n) { this.n = n; }
+                value() { return this.n; }
+            }
+            class B extend A {
+                value() { return super.value() * 10; }
+            }
+            var result = B(4).value();
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "result"), LValue::Number(40.0));
+    }
+
+    #[test]
+    fn unwind_to_handlers() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "
+            var log = [];
+            fun risky(n) {
+                var local = n;
+                if (n == 0) throw \"bottom\";
+                return risky(n - 1);
+            }
+            fun guarded() {
+                try { return risky(3); } finally { push(log, \"finally\"); }
+            }
+            var caught;
+            try { guarded(); } catch (e) { caught = e; }
+
+            var line;
+            for (var i = 0; i < 3; i = i + 1) {
+                try {
+                    if (i == 1) continue;
+                    line = nil.field;
+                } catch (e) {
+                    line = e.line;
+                } finally {
+                    push(log, i);
+                }
+            }
+            var after = [1, 2][1];
+        ").unwrap();
+
+        assert_eq!(global(&interpreter, "caught"), LValue::String(String::from("bottom")));
+        assert_eq!(global(&interpreter, "line"), LValue::Number(18.0));
+        assert_eq!(global(&interpreter, "after"), LValue::Number(2.0));
+
+        match global(&interpreter, "log") {
+            LValue::List(log) => assert_eq!(log.len(), 4),
+            value => panic!("Unexpected log {:?}", value),
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let source_expected: Vec<(&str, &str, usize)> = vec![
+            ("fun f(a) {}\nf();", "Expected 1 arguments but got 0.", 2),
+            ("var a = 1;\na();", "Can only call functions and classes.", 2),
+            ("print 1;\nprint -\"a\";", "Invalid negative operation for type string", 2),
+            ("class A {}\nA().x;", "Undefined property x", 2),
+        ];
+
+        for (source, message, line) in source_expected {
+            let mut interpreter = Interpreter::new();
+
+            match run(&mut interpreter, source) {
+                Err(RunTimeError::Error(error)) => {
+                    assert_eq!(error.message, message);
+                    assert_eq!(error.token.map(|token| token.line), Some(line));
+                },
+                result => panic!("expected an error for {}, got {:?}", source, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::runner::error::RunTimeError;
+use crate::value::LValue;
+use super::chunk::Chunk;
+
+/// Where a closure finds a captured variable, decided at compile time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpvalueDesc {
+    // Captured from a local of the enclosing function, or from its upvalues
+    pub is_local: bool,
+    pub index: u8,
+}
+
+/// Compiled body of a function, shared by all closures made from it
+pub struct FunctionProto {
+    pub name: String,
+    // Class a method is defined in
+    pub class: Option<String>,
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub upvalues: Vec<UpvalueDesc>,
+}
+
+impl FunctionProto {
+    /// Name qualified by the class for methods
+    pub fn qualified_name(&self) -> String {
+        match &self.class {
+            Some(class) => format!("{}.{}", class, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Variable captured by a closure. It points at the stack while the
+/// variable is alive there and holds the value once its scope ends.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(LValue),
+}
+
+pub struct Closure {
+    pub function: Rc<FunctionProto>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+pub struct ObjClass {
+    pub name: String,
+    pub methods: RefCell<HashMap<String, Rc<Closure>>>,
+}
+
+impl fmt::Display for ObjClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<ObjClass>,
+    pub fields: RefCell<HashMap<String, LValue>>,
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+pub struct BoundMethod {
+    pub receiver: LValue,
+    pub method: Rc<Closure>,
+}
+
+/// Values only the virtual machine creates
+#[derive(Clone)]
+pub enum Object {
+    // Only found in constant pools, the `Closure` instruction wraps it
+    Function(Rc<FunctionProto>),
+    Closure(Rc<Closure>),
+    Class(Rc<ObjClass>),
+    Instance(Rc<Instance>),
+    BoundMethod(Rc<BoundMethod>),
+    // Error unwinding through a finally clause, only ever held in a
+    // hidden local until the clause raises it again
+    Unwinding(Rc<RunTimeError>),
+}
+
+impl Object {
+    /// Name of the kind of value, as used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Object::Function(_) | Object::Closure(_) | Object::BoundMethod(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "class instance",
+            Object::Unwinding(_) => "error",
+        }
+    }
+}
+
+// Printed like the values of the tree-walking interpreter
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::Function(function) => write!(f, "Function(<fn {}>)", function.name),
+            Object::Closure(closure) => write!(f, "Function(<fn {}>)", closure.function.name),
+            Object::BoundMethod(bound) => write!(f, "Function(<fn {}>)", bound.method.function.name),
+            Object::Class(class) => write!(f, "Class({})", class),
+            Object::Instance(instance) => write!(f, "ClassInstance({})", instance),
+            Object::Unwinding(error) => write!(f, "Unwinding({:?})", error),
+        }
+    }
+}
+
+// Displayed like the values of the tree-walking interpreter
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::Function(function) => write!(f, "<fn {}>", function.name),
+            Object::Closure(closure) => write!(f, "<fn {}>", closure.function.name),
+            Object::BoundMethod(bound) => write!(f, "<fn {}>", bound.method.function.name),
+            Object::Class(class) => write!(f, "{}", class),
+            Object::Instance(instance) => write!(f, "{}", instance),
+            Object::Unwinding(error) => write!(f, "<unwinding {:?}>", error),
+        }
+    }
+}
+
+// Objects are only equal to themselves
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Function(a), Object::Function(b)) => Rc::ptr_eq(a, b),
+            (Object::Closure(a), Object::Closure(b)) => Rc::ptr_eq(a, b),
+            (Object::Class(a), Object::Class(b)) => Rc::ptr_eq(a, b),
+            (Object::Instance(a), Object::Instance(b)) => Rc::ptr_eq(a, b),
+            (Object::BoundMethod(a), Object::BoundMethod(b)) => Rc::ptr_eq(a, b),
+            (Object::Unwinding(a), Object::Unwinding(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+// Objects have no order
+impl PartialOrd for Object {
+    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
+        None
+    }
+}
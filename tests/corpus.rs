@@ -0,0 +1,75 @@
+//! Runs every script of `tests/corpus` with both engines. Each script states
+//! its output with `// expect: <line>` comments, and may end with a
+//! `// expect runtime error: <message>` comment.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ENGINES: [&str; 2] = ["tree", "vm"];
+const EXIT_SOFTWARE: i32 = 70;
+
+struct Expectation {
+    output: Vec<String>,
+    runtime_error: Option<String>,
+}
+
+fn expectation(source: &str) -> Expectation {
+    let mut output = Vec::new();
+    let mut runtime_error = None;
+
+    for line in source.lines() {
+        if let Some((_, expected)) = line.split_once("// expect: ") {
+            output.push(expected.to_string());
+        } else if let Some((_, message)) = line.split_once("// expect runtime error: ") {
+            runtime_error = Some(message.to_string());
+        }
+    }
+
+    Expectation { output, runtime_error }
+}
+
+fn scripts() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("corpus");
+    let mut scripts: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|extension| extension == "lox").unwrap_or(false))
+        .collect();
+
+    scripts.sort();
+    scripts
+}
+
+#[test]
+fn corpus() {
+    let scripts = scripts();
+    assert!(!scripts.is_empty());
+
+    for script in scripts {
+        let expected = expectation(&fs::read_to_string(&script).unwrap());
+
+        for engine in ENGINES {
+            let result = Command::new(env!("CARGO_BIN_EXE_craft_interpreter"))
+                .arg("--engine")
+                .arg(engine)
+                .arg(&script)
+                .output()
+                .unwrap();
+
+            let stdout = String::from_utf8_lossy(&result.stdout);
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            let context = format!("{} with engine {}\nstderr:\n{}", script.display(), engine, stderr);
+
+            assert_eq!(stdout.lines().collect::<Vec<_>>(), expected.output, "{}", context);
+
+            match &expected.runtime_error {
+                Some(message) => {
+                    assert_eq!(result.status.code(), Some(EXIT_SOFTWARE), "{}", context);
+                    assert!(stderr.contains(message.as_str()), "{}", context);
+                },
+                None => assert!(result.status.success(), "{}", context),
+            }
+        }
+    }
+}